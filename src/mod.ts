@@ -60,16 +60,202 @@ if (typeof Deno !== "undefined") {
 const WasmLoader: typeof WasmLoaderClass = _lib.DenoLoader;
 const WasmWorkspace: typeof WasmWorkspaceClass = _lib.DenoWorkspace;
 
+/** Request provided to a custom `fetch` function. */
+export interface FetchInit {
+  /** Headers to send with the request. */
+  headers: Record<string, string>;
+  /** Client certificate and CA config from the `.npmrc`, if any. */
+  clientCertConfig?: {
+    certfile?: string;
+    keyfile?: string;
+    /** Path to a file with CA certificates to trust. */
+    cafile?: string;
+    /** CA certificate to trust. */
+    ca?: string;
+  };
+  /**
+   * Signal that aborts when the `requestTimeout` elapses or when the
+   * signal of the operation making the request aborts.
+   */
+  signal?: AbortSignal;
+}
+
+/** Response returned from a custom `fetch` function. */
+export interface FetchResponse {
+  /** HTTP status code. */
+  status: number;
+  /** Body of the response. */
+  body: Uint8Array;
+  /** Headers of the response. */
+  headers: Headers;
+}
+
+/**
+ * Options for retrying failed downloads.
+ *
+ * Network errors, 429 responses, and 5xx responses are retried
+ * with an exponential backoff. A `Retry-After` header in seconds
+ * is respected.
+ */
+export interface RetryOptions {
+  /**
+   * Number of times to retry after the first attempt.
+   * @default 3
+   */
+  maxRetries?: number;
+  /**
+   * Delay in milliseconds before the first retry. This doubles on
+   * each following retry.
+   * @default 250
+   */
+  initialDelayMs?: number;
+  /**
+   * Maximum delay in milliseconds between retries.
+   * @default 10000
+   */
+  maxDelayMs?: number;
+}
+
+/** Progress of downloading and installing dependencies. */
+export type ProgressEvent =
+  | { kind: "downloadStart"; url: string }
+  | { kind: "downloadEnd"; url: string; status: number; bytes: number }
+  | { kind: "downloadFailed"; url: string; message: string }
+  /**
+   * A remote module or npm package (`npm:name@version`) was served from
+   * the cache or the server responded that the cached version is still
+   * valid. Npm packages are reported once installed, after the
+   * `npmPackageResolved` event.
+   */
+  | { kind: "cacheHit"; url: string }
+  /** An npm package (`name@version`) was added to the npm resolution. */
+  | { kind: "npmPackageResolved"; package: string }
+  | {
+    kind: "moduleLoaded";
+    specifier: string;
+    modulesDone: number;
+    modulesTotal: number;
+  }
+  /** Waiting on a lock held by another process (ex. for the npm cache). */
+  | { kind: "blocking"; message: string }
+  | { kind: "installingPackage"; message: string };
+
+/**
+ * What the loader may read and fetch.
+ *
+ * An omitted permission is unrestricted. Loads that aren't allowed
+ * error with the code `ERR_ACCESS_DENIED`.
+ */
+export interface LoaderPermissions {
+  /**
+   * Local paths that may be read. Files in the loader's npm cache and
+   * node_modules folder are always allowed.
+   */
+  read?: boolean | string[];
+  /**
+   * Hosts that may be fetched from (ex. `["jsr.io", "localhost:8000"]`).
+   * A host without a port allows any port. This also applies to
+   * redirects and npm registry downloads.
+   */
+  net?: boolean | string[];
+}
+
+/** An import map. */
+export interface ImportMap {
+  imports?: Record<string, string>;
+  scopes?: Record<string, Record<string, string>>;
+}
+
 /** Options for creating a workspace. */
 export interface WorkspaceOptions {
   /** Do not do config file discovery. */
   noConfig?: boolean;
   /** Do not respect the lockfile. */
   noLock?: boolean;
+  /**
+   * Error instead of updating the lockfile when adding entrypoints
+   * would change it.
+   *
+   * Defaults to the `lock.frozen` setting in the config file. Otherwise,
+   * newly discovered jsr, npm and remote dependencies are written to
+   * the lockfile once entrypoints are added.
+   */
+  frozenLockfile?: boolean;
   /** Path or file: URL to the config file if you do not want to do config file discovery. */
   configPath?: string;
+  /**
+   * Path or file: URL to the directory to use as the cache (`DENO_DIR`).
+   *
+   * Remote modules, npm packages and JSR metadata are cached here
+   * instead of in the global cache.
+   */
+  cacheDir?: string;
+  /**
+   * How npm packages are stored on the file system.
+   *
+   * - `"none"` - Use the global npm cache.
+   * - `"auto"` - Install npm packages into a local `node_modules` directory
+   *   when adding entrypoints.
+   * - `"manual"` - Use an existing `node_modules` directory (ex. one
+   *   installed by another package manager).
+   *
+   * Defaults to the `nodeModulesDir` setting in the config file.
+   */
+  nodeModulesDir?: "none" | "auto" | "manual";
+  /**
+   * Load remote modules from a `vendor` directory in the root of the
+   * workspace, populating it with any remote modules not yet vendored.
+   *
+   * Defaults to the `vendor` setting in the config file.
+   */
+  vendor?: boolean;
+  /**
+   * Path or file: URL to an import map or an inline import map object.
+   *
+   * The entries are applied on top of the `imports` and `scopes` of the
+   * root config file, taking precedence over the ones with the same key.
+   * Relative specifiers in an inline import map are resolved from the
+   * current working directory.
+   */
+  importMap?: string | ImportMap;
+  /**
+   * How to detect whether a `.js` file is CommonJS.
+   *
+   * - `"explicit"` - Only when the closest package.json has
+   *   `"type": "commonjs"`.
+   * - `"implicit"` - Also when the closest package.json has no `"type"` and
+   *   the file is a script (ex. uses `require` or `module.exports`), which
+   *   matches Node.js.
+   * - `"disabled"` - Never treat `.js` files as CommonJS.
+   *
+   * @default "explicit"
+   */
+  cjsDetection?: "explicit" | "implicit" | "disabled";
+  /**
+   * Translate CommonJS modules to ES modules when loading.
+   *
+   * The loaded code is an ESM wrapper that re-exports the statically
+   * analyzed named exports of the CommonJS module. Analysis results are
+   * cached in memory for all loaders created from the workspace and, when
+   * `cacheDir` is provided, on disk so they're reused across processes.
+   */
+  translateCjs?: boolean;
   /** Node resolution conditions to use for resolving package.json exports. */
   nodeConditions?: string[];
+  /**
+   * Conditions to use instead of the default conditions when resolving
+   * package.json exports for an import (ex. `["worker", "import"]`).
+   *
+   * Conditions in `nodeConditions` are still used.
+   */
+  importConditions?: string[];
+  /**
+   * Conditions to use instead of the default conditions when resolving
+   * package.json exports for a require (ex. `["require"]`).
+   *
+   * Conditions in `nodeConditions` are still used.
+   */
+  requireConditions?: string[];
   /** Date for the newest allowed dependency. */
   newestDependencyDate?: Date;
   /**
@@ -77,7 +263,70 @@ export interface WorkspaceOptions {
    * @default "node"
    */
   platform?: "node" | "browser";
-  /** Whether to force using the cache. */
+  /**
+   * Function to use instead of the global `fetch` for downloading remote
+   * modules and npm packages (ex. to use a proxy or serve from a mirror).
+   *
+   * Redirects should not be followed. Return the redirect response instead.
+   */
+  fetch?: (
+    url: string,
+    init: FetchInit,
+  ) => Promise<FetchResponse | Response>;
+  /**
+   * Options for retrying failed downloads of remote modules and
+   * npm packages.
+   */
+  retry?: RetryOptions;
+  /**
+   * Auth tokens to use for remote modules in the same format as the
+   * `DENO_AUTH_TOKENS` environment variable.
+   *
+   * This is a semi-colon separated list of bearer tokens (`token@host`)
+   * or basic auth credentials (`username:password@host`). A host also
+   * matches its subdomains and may include a port.
+   *
+   * @example `"a1b2c3d4@raw.githubusercontent.com;user:pw@example.com:8080"`
+   */
+  authTokens?: string;
+  /**
+   * Maximum number of remote modules and npm packages to download at
+   * the same time. Downloads are not limited when not provided.
+   *
+   * Identical requests that are in flight are always shared.
+   */
+  maxConcurrentDownloads?: number;
+  /**
+   * Milliseconds to wait for a download to complete before aborting it.
+   * Aborted downloads are retried according to the `retry` options.
+   */
+  requestTimeout?: number;
+  /** Restricts what local files and remote hosts the loader may load. */
+  permissions?: LoaderPermissions;
+  /**
+   * Callback for progress events. When provided, nothing is logged
+   * to the console for downloads or npm installs.
+   */
+  onProgress?: (event: ProgressEvent) => void;
+  /** Don't log downloads or npm installs to the console. */
+  quiet?: boolean;
+  /**
+   * How to use the cache for remote modules, jsr metadata, and npm packages.
+   *
+   * - `"use"` - Use the cache and only download what's missing.
+   * - `"only"` - Only use the cache and error on anything missing.
+   * - `"reload"` - Download everything again.
+   * - `string[]` - Download again the specifiers that start with one of
+   *   these prefixes (ex. `["https://deno.land/std/", "npm:chalk"]`).
+   *
+   * @default "use"
+   */
+  cacheSetting?: "use" | "only" | "reload" | string[];
+  /**
+   * Whether to force using the cache.
+   *
+   * @deprecated Use `cacheSetting: "only"` instead.
+   */
   cachedOnly?: boolean;
   /**
    * Enable debug logs.
@@ -87,10 +336,27 @@ export interface WorkspaceOptions {
    * created. This is a limitation of how the Rust logging works.
    */
   debug?: boolean;
+  /**
+   * How to provide the source maps of transpiled modules.
+   *
+   * - `"inline"` - Keep the inline `sourceMappingURL` comment in the code.
+   * - `"external"` - Remove the comment from the code so the source map
+   *   is only provided via `ModuleLoadResponse.sourceMap`.
+   *
+   * @default "inline"
+   */
+  sourceMap?: "inline" | "external";
   /** Whether to preserve JSX syntax in the loaded output. */
   preserveJsx?: boolean;
   /** Skip transpiling TypeScript and JSX. */
   noTranspile?: boolean;
+  /**
+   * Include type dependencies in the module graph.
+   *
+   * This is necessary for resolving `@ts-types` directives and
+   * `X-TypeScript-Types` headers when using `ResolutionKind.Types`.
+   */
+  includeTypes?: boolean;
 }
 
 export class ResolveError extends Error {
@@ -175,9 +441,10 @@ export interface ModuleLoadResponse {
    * Source map for the loaded code, if available.
    *
    * This is the decoded JSON source map extracted from inline source maps
-   * that are generated during transpilation. It will be `undefined` for
-   * non-transpiled files (e.g. JavaScript, JSON, CSS) or when transpilation
-   * is disabled via `noTranspile`.
+   * that are generated during transpilation. For files that aren't
+   * transpiled (e.g. JavaScript and CSS), this is the source map
+   * referenced by a trailing `sourceMappingURL` comment, if any. It will
+   * be `undefined` for JSON or when there is no source map.
    */
   sourceMap?: Uint8Array;
 }
@@ -190,6 +457,14 @@ export enum ResolutionMode {
   Require = 1,
 }
 
+/** What the resolution is for. */
+export enum ResolutionKind {
+  /** Resolving the code that will be executed. */
+  Execution = 0,
+  /** Resolving the type declarations (ex. `.d.ts` files) of a specifier. */
+  Types = 1,
+}
+
 /** Resolves the workspace. */
 export class Workspace implements Disposable {
   #inner: WasmWorkspace;
@@ -201,6 +476,10 @@ export class Workspace implements Disposable {
     this.#debug = options.debug ?? false;
   }
 
+  /**
+   * Frees the workspace. The HTTP clients created for `.npmrc` client
+   * certificates are closed once its loaders are disposed as well.
+   */
   [Symbol.dispose]() {
     this.#inner.free();
   }
@@ -219,8 +498,95 @@ export enum RequestedModuleType {
   Bytes = 3,
 }
 
+/** Options for adding entrypoints. */
+export interface AddEntrypointsOptions {
+  /**
+   * Also surface diagnostics for modules that are only reachable
+   * via dynamic imports (ex. code split chunks).
+   *
+   * Dynamic imports are always loaded into the module graph so they
+   * resolve the same way as static imports.
+   */
+  followDynamicImports?: boolean;
+  /**
+   * Signal to cancel building the module graph. This also aborts the
+   * in-flight downloads of remote modules unless another operation is
+   * waiting on the same download.
+   *
+   * Downloads of npm packages and registry information aren't aborted,
+   * but the operation stops waiting on them.
+   */
+  signal?: AbortSignal;
+}
+
+/** Options for resolving a specifier asynchronously. */
+export interface ResolveOptions {
+  /** Signal to cancel the resolution. */
+  signal?: AbortSignal;
+}
+
+/** Options for loading a specifier. */
+export interface LoadOptions {
+  /** Signal to cancel the load. */
+  signal?: AbortSignal;
+}
+
+/** Kind of entrypoint diagnostic. */
+export type EntrypointDiagnosticKind =
+  | "resolution"
+  | "load"
+  | "parse"
+  | "jsr"
+  | "npm";
+
+/** One-based line and column in a file. */
+export interface DiagnosticPosition {
+  line: number;
+  column: number;
+}
+
+/** A diagnostic surfaced when adding entrypoints. */
 export interface EntrypointDiagnostic {
+  /** Kind of diagnostic. */
+  kind: EntrypointDiagnosticKind;
+  /**
+   * Stable error code (ex. `ERR_MODULE_NOT_FOUND`).
+   *
+   * Node.js error codes are used for resolution errors when available.
+   */
+  code: string;
+  /** Message of the diagnostic including the range. */
+  message: string;
+  /**
+   * Specifier of the module that errored or, for resolution errors, the
+   * specifier of the import that couldn't be resolved.
+   */
+  specifier?: string;
+  /** Specifier of the module that contains the import, if applicable. */
+  referrer?: string;
+  /** Start of the import in the referrer. */
+  start?: DiagnosticPosition;
+  /** End of the import in the referrer. */
+  end?: DiagnosticPosition;
+}
+
+/**
+ * A warning that occurred while resolving a specifier via the workspace
+ * config or import map (ex. a version constraint not matching the version
+ * of a workspace member).
+ */
+export interface ResolutionDiagnostic {
+  /** Message of the diagnostic. */
   message: string;
+  /** Specifier of the module that contains the import. */
+  referrer: string;
+  /** Start of the import in the referrer. */
+  start: DiagnosticPosition;
+  /**
+   * Config file (deno.json or package.json) of the workspace member
+   * the referrer is in.
+   */
+  configFile?: string;
 }
 
 /** A loader for resolving and loading urls. */
@@ -241,17 +607,65 @@ export class Loader implements Disposable {
     this.#inner.free();
   }
 
+  /** Adds or replaces an in-memory file.
+   *
+   * In-memory files take precedence over files on disk or remote modules
+   * and participate in graph building, resolution and loading like any
+   * other module. Add them before adding entrypoints that import them
+   * and call `invalidate` after replacing a file that was already loaded.
+   *
+   * @param specifier Path or `file:`/`http:`/`https:` URL of the file.
+   * @param source Source code of the file.
+   * @param mediaType Media type of the file. When not provided, it's
+   * inferred from the specifier's extension. Media types that are a
+   * variant of another one (ex. `Cjs` or `Dts`) require the specifier
+   * to have a matching extension.
+   */
+  addMemoryFile(
+    specifier: string,
+    source: string | Uint8Array,
+    mediaType?: MediaType,
+  ): void {
+    this.#inner.add_memory_file(
+      specifier,
+      typeof source === "string" ? new TextEncoder().encode(source) : source,
+      mediaType,
+    );
+  }
+
+  /** Removes an in-memory file previously added with `addMemoryFile`.
+   *
+   * @returns If a file was removed.
+   */
+  removeMemoryFile(specifier: string): boolean {
+    return this.#inner.remove_memory_file(specifier);
+  }
+
   /** Adds entrypoints to the loader.
    *
    * It's useful to specify entrypoints so that the loader can resolve
    * npm: and jsr: specifiers the same way that Deno does when not using
    * a lockfile.
    */
-  async addEntrypoints(
+  addEntrypoints(
     entrypoints: string[],
+    options?: AddEntrypointsOptions,
   ): Promise<EntrypointDiagnostic[]> {
-    const messages = await this.#inner.add_entrypoints(entrypoints);
-    return messages.map((message) => ({ message }));
+    return this.#inner.add_entrypoints(entrypoints, options);
+  }
+
+  /** Invalidates the provided changed files.
+   *
+   * This removes the files and any modules that depend on them from the
+   * module graph. The removed modules are rebuilt on the next call to
+   * `addEntrypoints`, which only reparses the files that changed.
+   * Invalidating a package.json also invalidates the modules in its
+   * directory since their imports may resolve differently.
+   *
+   * @param specifiers Paths or URLs of the files that changed.
+   */
+  invalidate(specifiers: string[]): Promise<void> {
+    return this.#inner.invalidate(specifiers);
   }
 
   /** Synchronously resolves a specifier using the given referrer and resolution mode.
@@ -261,12 +675,15 @@ export class Loader implements Disposable {
     specifier: string,
     referrer: string | undefined,
     resolutionMode: ResolutionMode,
+    resolutionKind: ResolutionKind = ResolutionKind.Execution,
   ): string {
     if (this.#debug) {
       console.error(
         `DEBUG - Resolving '${specifier}' from '${
           referrer ?? "<undefined>"
-        }' (${resolutionModeToString(resolutionMode)})`,
+        }' (${resolutionModeToString(resolutionMode)}${
+          resolutionKind === ResolutionKind.Types ? ", types" : ""
+        })`,
       );
     }
     try {
@@ -274,6 +691,7 @@ export class Loader implements Disposable {
         specifier,
         referrer,
         resolutionMode,
+        resolutionKind,
       );
       if (this.#debug) {
         console.error(`DEBUG - Resolved to '${value}'`);
@@ -299,12 +717,16 @@ export class Loader implements Disposable {
     specifier: string,
     referrer: string | undefined,
     resolutionMode: ResolutionMode,
+    resolutionKind: ResolutionKind = ResolutionKind.Execution,
+    options?: ResolveOptions,
   ): Promise<string> {
     if (this.#debug) {
       console.error(
         `DEBUG - Resolving '${specifier}' from '${
           referrer ?? "<undefined>"
-        }' (${resolutionModeToString(resolutionMode)})`,
+        }' (${resolutionModeToString(resolutionMode)}${
+          resolutionKind === ResolutionKind.Types ? ", types" : ""
+        })`,
       );
     }
     try {
@@ -312,6 +734,8 @@ export class Loader implements Disposable {
         specifier,
         referrer,
         resolutionMode,
+        resolutionKind,
+        options?.signal,
       );
       if (this.#debug) {
         console.error(`DEBUG - Resolved to '${value}'`);
@@ -327,6 +751,7 @@ export class Loader implements Disposable {
   load(
     specifier: string,
     requestedModuleType: RequestedModuleType,
+    options?: LoadOptions,
   ): Promise<LoadResponse> {
     if (this.#debug) {
       console.error(
@@ -335,7 +760,16 @@ export class Loader implements Disposable {
         }'`,
       );
     }
-    return this.#inner.load(specifier, requestedModuleType);
+    return this.#inner.load(specifier, requestedModuleType, options?.signal);
+  }
+
+  /** Takes the resolution diagnostics that occurred since the last call.
+   *
+   * These are warnings that don't cause resolution to fail. Each loader
+   * only gets the diagnostics of its own resolutions.
+   */
+  takeResolutionDiagnostics(): ResolutionDiagnostic[] {
+    return this.#inner.take_resolution_diagnostics();
   }
 
   /** Gets the module graph.
@@ -0,0 +1,90 @@
+use std::future::Future;
+use std::pin::pin;
+use std::task::Poll;
+
+use wasm_bindgen::JsValue;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen_futures::JsFuture;
+
+#[wasm_bindgen(module = "/helpers.js")]
+extern "C" {
+  type AbortWaiter;
+
+  fn wait_for_abort(signal: &JsValue) -> AbortWaiter;
+
+  #[wasm_bindgen(method, getter)]
+  fn promise(this: &AbortWaiter) -> js_sys::Promise;
+
+  #[wasm_bindgen(method)]
+  fn dispose(this: &AbortWaiter);
+}
+
+/// Removes the abort listener once the operation completes or is dropped.
+struct AbortWaiterGuard(AbortWaiter);
+
+impl Drop for AbortWaiterGuard {
+  fn drop(&mut self) {
+    self.0.dispose();
+  }
+}
+
+/// Runs the future until it completes or the provided `AbortSignal`
+/// aborts, in which case the future is dropped and the signal's
+/// reason is returned as the error.
+pub async fn with_abort_signal<T>(
+  signal: &JsValue,
+  future: impl Future<Output = Result<T, JsValue>>,
+) -> Result<T, JsValue> {
+  if signal.is_undefined() || signal.is_null() {
+    return future.await;
+  }
+  let waiter = AbortWaiterGuard(wait_for_abort(signal));
+  let mut future = pin!(future);
+  let mut aborted = pin!(JsFuture::from(waiter.0.promise()));
+  std::future::poll_fn(|cx| {
+    if let Poll::Ready(result) = future.as_mut().poll(cx) {
+      return Poll::Ready(result);
+    }
+    match aborted.as_mut().poll(cx) {
+      Poll::Ready(Ok(reason) | Err(reason)) => Poll::Ready(Err(reason)),
+      Poll::Pending => Poll::Pending,
+    }
+  })
+  .await
+}
+
+/// An `AbortSignal` that can be stored in the types that the Deno
+/// crates require to be `Send + Sync`.
+#[derive(Debug, Clone)]
+pub struct SyncAbortSignal(JsValue);
+
+// SAFETY: Wasm is single threaded, so the signal is never accessed
+// from another thread.
+unsafe impl Send for SyncAbortSignal {}
+unsafe impl Sync for SyncAbortSignal {}
+
+impl SyncAbortSignal {
+  pub fn from_js_value(value: &JsValue) -> Option<Self> {
+    if value.is_undefined() || value.is_null() {
+      None
+    } else {
+      Some(Self(value.clone()))
+    }
+  }
+
+  pub fn is_aborted(&self) -> bool {
+    js_sys::Reflect::get(&self.0, &JsValue::from_str("aborted"))
+      .ok()
+      .and_then(|value| value.as_bool())
+      .unwrap_or(false)
+  }
+
+  pub fn reason(&self) -> JsValue {
+    js_sys::Reflect::get(&self.0, &JsValue::from_str("reason"))
+      .unwrap_or(JsValue::UNDEFINED)
+  }
+
+  pub fn as_js_value(&self) -> &JsValue {
+    &self.0
+  }
+}
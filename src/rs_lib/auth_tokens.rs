@@ -0,0 +1,110 @@
+use base64::Engine as _;
+use url::Url;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AuthTokenData {
+  Bearer(String),
+  Basic { username: String, password: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AuthToken {
+  host: String,
+  data: AuthTokenData,
+}
+
+impl AuthToken {
+  fn matches(&self, url: &Url) -> bool {
+    let Some(host) = url.host_str() else {
+      return false;
+    };
+    let host = match url.port() {
+      Some(port) => format!("{}:{}", host, port),
+      None => host.to_string(),
+    };
+    host_matches(&host, &self.host)
+  }
+
+  fn header_value(&self) -> String {
+    match &self.data {
+      AuthTokenData::Bearer(token) => format!("Bearer {}", token),
+      AuthTokenData::Basic { username, password } => {
+        let credentials = format!("{}:{}", username, password);
+        format!(
+          "Basic {}",
+          base64::engine::general_purpose::STANDARD.encode(credentials)
+        )
+      }
+    }
+  }
+}
+
+fn host_matches(url_host: &str, token_host: &str) -> bool {
+  let url_host = url_host.to_ascii_lowercase();
+  let token_host = token_host.to_ascii_lowercase();
+  url_host == token_host
+    || url_host
+      .strip_suffix(&token_host)
+      .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Auth tokens for remote hosts in the same format as the
+/// `DENO_AUTH_TOKENS` environment variable, which is a
+/// semi-colon separated list of `token@host` (bearer) or
+/// `username:password@host` (basic) entries.
+#[derive(Debug, Default, Clone)]
+pub struct AuthTokens(Vec<AuthToken>);
+
+impl AuthTokens {
+  pub fn new(value: &str) -> Result<Self, anyhow::Error> {
+    let mut tokens = Vec::new();
+    for entry in value.split(';') {
+      let entry = entry.trim();
+      if entry.is_empty() {
+        continue;
+      }
+      let Some((credentials, host)) =
+        entry.rsplit_once('@').filter(|(credentials, host)| {
+          !credentials.is_empty() && !host.is_empty()
+        })
+      else {
+        anyhow::bail!(
+          concat!(
+            "Invalid auth token entry '{}'. ",
+            "Expected 'token@host' or 'username:password@host'."
+          ),
+          redact_entry(entry)
+        );
+      };
+      let data = match credentials.split_once(':') {
+        Some((username, password)) => AuthTokenData::Basic {
+          username: username.to_string(),
+          password: password.to_string(),
+        },
+        None => AuthTokenData::Bearer(credentials.to_string()),
+      };
+      tokens.push(AuthToken {
+        host: host.to_string(),
+        data,
+      });
+    }
+    Ok(Self(tokens))
+  }
+
+  /// Gets the value of the `Authorization` header to use for the url.
+  pub fn header_value_for(&self, url: &Url) -> Option<String> {
+    self
+      .0
+      .iter()
+      .find(|token| token.matches(url))
+      .map(|token| token.header_value())
+  }
+}
+
+/// Hides the credentials of an entry so they don't end up in error messages.
+fn redact_entry(entry: &str) -> String {
+  match entry.rsplit_once('@') {
+    Some((_, host)) => format!("***@{}", host),
+    None => "***".to_string(),
+  }
+}
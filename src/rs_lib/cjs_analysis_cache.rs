@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use deno_resolver::cjs::analyzer::ModuleExportsAndReExports;
+use deno_resolver::cjs::analyzer::NodeAnalysisCache;
+use serde::Deserialize;
+use serde::Serialize;
+use sys_traits::FsCreateDirAll;
+use sys_traits::FsRead;
+use sys_traits::FsWrite;
+use sys_traits::impls::RealSys;
+use url::Url;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+  specifier: String,
+  source_hash: String,
+  exports: Vec<String>,
+  reexports: Vec<String>,
+}
+
+/// Caches the exports analyzed from CommonJS modules so that they
+/// don't need to be analyzed again when loaded by another loader.
+///
+/// When a folder is provided, the analyses are also stored on disk
+/// so that they're reused across processes.
+#[derive(Debug)]
+pub struct CjsAnalysisCache {
+  sys: RealSys,
+  dir: Option<PathBuf>,
+  analyses: Mutex<HashMap<Url, (String, ModuleExportsAndReExports)>>,
+}
+
+impl CjsAnalysisCache {
+  pub fn new(sys: RealSys, dir: Option<PathBuf>) -> Self {
+    Self {
+      sys,
+      dir,
+      analyses: Default::default(),
+    }
+  }
+
+  fn entry_path(&self, specifier: &Url) -> Option<PathBuf> {
+    let dir = self.dir.as_ref()?;
+    Some(dir.join(format!("{:016x}.json", stable_hash(specifier.as_str()))))
+  }
+
+  fn read_entry(&self, specifier: &Url) -> Option<CacheEntry> {
+    let path = self.entry_path(specifier)?;
+    let text = self.sys.fs_read_to_string(&path).ok()?;
+    let entry = serde_json::from_str::<CacheEntry>(&text).ok()?;
+    // guard against hash collisions
+    (entry.specifier == specifier.as_str()).then_some(entry)
+  }
+
+  fn write_entry(&self, entry: &CacheEntry, specifier: &Url) {
+    let Some(path) = self.entry_path(specifier) else {
+      return;
+    };
+    let result = (|| {
+      if let Some(parent) = path.parent() {
+        self.sys.fs_create_dir_all(parent)?;
+      }
+      let text = serde_json::to_string(entry)?;
+      self.sys.fs_write(&path, text)?;
+      Ok::<_, anyhow::Error>(())
+    })();
+    if let Err(err) = result {
+      log::debug!(
+        "Failed caching CommonJS analysis of {}: {:#}",
+        specifier,
+        err
+      );
+    }
+  }
+}
+
+impl NodeAnalysisCache for CjsAnalysisCache {
+  fn compute_source_hash(&self, source: &str) -> String {
+    format!("{:016x}", stable_hash(source))
+  }
+
+  fn get_cjs_analysis(
+    &self,
+    specifier: &Url,
+    expected_source_hash: &str,
+  ) -> Option<ModuleExportsAndReExports> {
+    {
+      let analyses = self.analyses.lock().unwrap();
+      if let Some((source_hash, analysis)) = analyses.get(specifier) {
+        return (source_hash == expected_source_hash).then(|| analysis.clone());
+      }
+    }
+    let entry = self.read_entry(specifier)?;
+    if entry.source_hash != expected_source_hash {
+      return None;
+    }
+    let analysis = ModuleExportsAndReExports {
+      exports: entry.exports,
+      reexports: entry.reexports,
+    };
+    self
+      .analyses
+      .lock()
+      .unwrap()
+      .insert(specifier.clone(), (entry.source_hash, analysis.clone()));
+    Some(analysis)
+  }
+
+  fn set_cjs_analysis(
+    &self,
+    specifier: &Url,
+    source_hash: &str,
+    analysis: &ModuleExportsAndReExports,
+  ) {
+    self.analyses.lock().unwrap().insert(
+      specifier.clone(),
+      (source_hash.to_string(), analysis.clone()),
+    );
+    self.write_entry(
+      &CacheEntry {
+        specifier: specifier.to_string(),
+        source_hash: source_hash.to_string(),
+        exports: analysis.exports.clone(),
+        reexports: analysis.reexports.clone(),
+      },
+      specifier,
+    );
+  }
+}
+
+/// FNV-1a, which unlike `DefaultHasher` is stable across Rust
+/// versions so the hashes can be persisted.
+fn stable_hash(value: &str) -> u64 {
+  let mut hash: u64 = 0xcbf29ce484222325;
+  for byte in value.as_bytes() {
+    hash ^= *byte as u64;
+    hash = hash.wrapping_mul(0x100000001b3);
+  }
+  hash
+}
@@ -0,0 +1,256 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use deno_error::JsErrorClass;
+use deno_graph::ModuleErrorKind;
+use deno_graph::ModuleGraphError;
+use deno_graph::ModuleLoadError;
+use deno_graph::Position;
+use deno_graph::ResolutionError;
+use deno_graph::SpecifierError;
+use deno_resolver::DenoResolveError;
+use deno_resolver::MappedResolutionDiagnosticWithPosition;
+use serde::Serialize;
+
+use crate::deno_resolve_error_code;
+use crate::permissions::ACCESS_DENIED_CLASS;
+use crate::permissions::ACCESS_DENIED_CODE;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EntrypointDiagnosticKind {
+  Resolution,
+  Load,
+  Parse,
+  Jsr,
+  Npm,
+}
+
+/// One-based line and column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticPosition {
+  pub line: usize,
+  pub column: usize,
+}
+
+impl From<Position> for DiagnosticPosition {
+  fn from(position: Position) -> Self {
+    Self {
+      line: position.line + 1,
+      column: position.character + 1,
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntrypointDiagnostic {
+  pub kind: EntrypointDiagnosticKind,
+  pub code: &'static str,
+  pub message: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub specifier: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub referrer: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub start: Option<DiagnosticPosition>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub end: Option<DiagnosticPosition>,
+}
+
+impl EntrypointDiagnostic {
+  pub fn from_graph_error(err: &ModuleGraphError) -> Self {
+    let (kind, code, specifier) = match err {
+      ModuleGraphError::ResolutionError(err)
+      | ModuleGraphError::TypesResolutionError(err) => (
+        EntrypointDiagnosticKind::Resolution,
+        resolution_error_code(err),
+        resolution_error_specifier(err),
+      ),
+      ModuleGraphError::ModuleError(err) => {
+        let (kind, code) = module_error_kind_and_code(err.as_kind());
+        (kind, code, Some(err.specifier().to_string()))
+      }
+    };
+    let maybe_range = err.maybe_range();
+    Self {
+      kind,
+      code,
+      message: err.to_string_with_range(),
+      specifier,
+      referrer: maybe_range.map(|r| r.specifier.to_string()),
+      start: maybe_range.map(|r| r.range.start.into()),
+      end: maybe_range.map(|r| r.range.end.into()),
+    }
+  }
+}
+
+fn resolution_error_code(err: &ResolutionError) -> &'static str {
+  match err {
+    ResolutionError::InvalidDowngrade { .. } => "ERR_INVALID_DOWNGRADE",
+    ResolutionError::InvalidJsrHttpsTypesImport { .. } => {
+      "ERR_INVALID_JSR_HTTPS_TYPES_IMPORT"
+    }
+    ResolutionError::InvalidLocalImport { .. } => "ERR_INVALID_LOCAL_IMPORT",
+    ResolutionError::InvalidSpecifier { .. } => "ERR_INVALID_SPECIFIER",
+    ResolutionError::ResolverError { .. } => err
+      .source()
+      .and_then(|s| s.downcast_ref::<DenoResolveError>())
+      .and_then(deno_resolve_error_code)
+      .map(|code| code.as_str())
+      .unwrap_or("ERR_UNRESOLVED_SPECIFIER"),
+  }
+}
+
+/// The specifier as written in the import that failed to resolve.
+fn resolution_error_specifier(err: &ResolutionError) -> Option<String> {
+  match err {
+    ResolutionError::InvalidDowngrade { specifier, .. }
+    | ResolutionError::InvalidJsrHttpsTypesImport { specifier, .. }
+    | ResolutionError::InvalidLocalImport { specifier, .. } => {
+      Some(specifier.to_string())
+    }
+    ResolutionError::InvalidSpecifier { error, .. } => match error {
+      SpecifierError::ImportPrefixMissing { specifier, .. } => {
+        Some(specifier.clone())
+      }
+      SpecifierError::InvalidUrl(_) => None,
+    },
+    ResolutionError::ResolverError { specifier, .. } => Some(specifier.clone()),
+  }
+}
+
+fn module_error_kind_and_code(
+  kind: &ModuleErrorKind,
+) -> (EntrypointDiagnosticKind, &'static str) {
+  match kind {
+    ModuleErrorKind::Load { err, .. }
+      if err.get_class() == ACCESS_DENIED_CLASS =>
+    {
+      (EntrypointDiagnosticKind::Load, ACCESS_DENIED_CODE)
+    }
+    ModuleErrorKind::Load { err, .. } => match err {
+      ModuleLoadError::Jsr(_) => {
+        (EntrypointDiagnosticKind::Jsr, "ERR_JSR_LOAD")
+      }
+      ModuleLoadError::Npm(_) => {
+        (EntrypointDiagnosticKind::Npm, "ERR_NPM_LOAD")
+      }
+      _ => (EntrypointDiagnosticKind::Load, "ERR_LOAD"),
+    },
+    ModuleErrorKind::Missing { .. }
+    | ModuleErrorKind::MissingDynamic { .. } => {
+      (EntrypointDiagnosticKind::Load, "ERR_MODULE_NOT_FOUND")
+    }
+    ModuleErrorKind::Parse { .. } => {
+      (EntrypointDiagnosticKind::Parse, "ERR_PARSE")
+    }
+    ModuleErrorKind::WasmParse { .. } => {
+      (EntrypointDiagnosticKind::Parse, "ERR_WASM_PARSE")
+    }
+    ModuleErrorKind::UnsupportedMediaType { .. } => {
+      (EntrypointDiagnosticKind::Load, "ERR_UNSUPPORTED_MEDIA_TYPE")
+    }
+    ModuleErrorKind::InvalidTypeAssertion { .. } => {
+      (EntrypointDiagnosticKind::Load, "ERR_INVALID_TYPE_ASSERTION")
+    }
+    ModuleErrorKind::UnsupportedImportAttributeType { .. } => (
+      EntrypointDiagnosticKind::Load,
+      "ERR_UNSUPPORTED_IMPORT_ATTRIBUTE_TYPE",
+    ),
+  }
+}
+
+/// A non-fatal diagnostic that occurred while resolving a specifier
+/// via the workspace config or import map.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolutionDiagnostic {
+  pub message: String,
+  pub referrer: String,
+  pub start: DiagnosticPosition,
+  /// The config file of the workspace member the referrer is in.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub config_file: Option<String>,
+}
+
+impl ResolutionDiagnostic {
+  pub fn from_mapped_resolution_diagnostic(
+    diagnostic: &MappedResolutionDiagnosticWithPosition,
+    config_file: Option<String>,
+  ) -> Self {
+    Self {
+      message: diagnostic.diagnostic.to_string(),
+      referrer: diagnostic.referrer.to_string(),
+      start: diagnostic.start.into(),
+      config_file,
+    }
+  }
+}
+
+/// Resolution diagnostics raised by the resolver that's shared by the
+/// loaders of a workspace. They're moved to the loader that was
+/// running once its synchronous work completes.
+#[derive(Debug, Default, Clone)]
+pub struct PendingResolutionDiagnostics(Arc<Mutex<Vec<ResolutionDiagnostic>>>);
+
+impl PendingResolutionDiagnostics {
+  pub fn push(&self, diagnostic: ResolutionDiagnostic) {
+    self.0.lock().unwrap().push(diagnostic);
+  }
+}
+
+/// The resolution diagnostics of a loader.
+#[derive(Debug)]
+pub struct ResolutionDiagnostics {
+  pending: PendingResolutionDiagnostics,
+  diagnostics: RefCell<Vec<ResolutionDiagnostic>>,
+}
+
+impl ResolutionDiagnostics {
+  pub fn new(pending: PendingResolutionDiagnostics) -> Self {
+    Self {
+      pending,
+      diagnostics: Default::default(),
+    }
+  }
+
+  pub fn take(&self) -> Vec<ResolutionDiagnostic> {
+    std::mem::take(&mut *self.diagnostics.borrow_mut())
+  }
+
+  /// Collects the diagnostics that occur while running the function.
+  pub fn collect<R>(&self, f: impl FnOnce() -> R) -> R {
+    // drop any diagnostics that weren't raised by a loader's operation
+    self.pending.0.lock().unwrap().clear();
+    let result = f();
+    self.take_pending();
+    result
+  }
+
+  /// Collects the diagnostics that occur while polling the future.
+  ///
+  /// Each poll runs synchronously on the single wasm thread, so the
+  /// diagnostics raised during it belong to this loader even when
+  /// the operations of several loaders are interleaved.
+  pub async fn collect_async<T>(&self, future: impl Future<Output = T>) -> T {
+    let mut future = pin!(future);
+    std::future::poll_fn(|cx| self.collect(|| future.as_mut().poll(cx))).await
+  }
+
+  fn take_pending(&self) {
+    let pending = std::mem::take(&mut *self.pending.0.lock().unwrap());
+    let mut diagnostics = self.diagnostics.borrow_mut();
+    for diagnostic in pending {
+      // the same specifier may be resolved many times
+      if !diagnostics.contains(&diagnostic) {
+        diagnostics.push(diagnostic);
+      }
+    }
+  }
+}
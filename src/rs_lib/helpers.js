@@ -1,43 +1,224 @@
-export async function fetch_specifier(specifier, headers, clientCertConfig) {
-  let client;
+export async function fetch_specifier(
+  specifier,
+  headers,
+  clientCertConfig,
+  clientsId,
+  customFetch,
+  timeoutMs,
+  abortSignal,
+) {
   try {
-    console.error("Downloading", specifier);
-    const options = {
-      headers,
-      redirect: "manual",
-    };
-
+    const signals = [];
+    if (timeoutMs != null) {
+      signals.push(AbortSignal.timeout(timeoutMs));
+    }
+    if (abortSignal) {
+      signals.push(abortSignal);
+    }
+    const signal = signals.length > 1 ? AbortSignal.any(signals) : signals[0];
+    if (customFetch) {
+      const response = await customFetch(specifier, {
+        headers,
+        clientCertConfig: clientCertConfig ?? undefined,
+        signal,
+      });
+      return {
+        status: response.status,
+        body: response instanceof Response
+          ? await response.bytes()
+          : response.body,
+        headers: response.headers,
+      };
+    }
     if (clientCertConfig) {
-      client = await createClientCertHttpClient(clientCertConfig);
-      if (client) {
-        options.client = client;
+      const client = await getClientCertHttpClient(
+        clientsId,
+        clientCertConfig,
+      );
+      if (client?.kind === "node") {
+        return await nodeHttpsRequest(
+          specifier,
+          headers,
+          client.agent,
+          signal,
+        );
       }
+      return await fetchSpecifier(specifier, headers, client?.client, signal);
     }
-
-    const response = await fetch(specifier, options);
-    const status = response.status;
-    const body = await response.bytes();
-    return {
-      status,
-      body,
-      headers: response.headers,
-    };
+    return await fetchSpecifier(specifier, headers, undefined, signal);
   } catch (err) {
     return {
       error: err.toString(),
     };
-  } finally {
-    if (client) {
-      client.close();
-    }
+  }
+}
+
+async function fetchSpecifier(specifier, headers, client, signal) {
+  const options = {
+    headers,
+    redirect: "manual",
+    signal,
+  };
+  if (client) {
+    options.client = client;
+  }
+  const response = await fetch(specifier, options);
+  const status = response.status;
+  const body = await response.bytes();
+  return {
+    status,
+    body,
+    headers: response.headers,
+  };
+}
+
+export function sleep(ms) {
+  return new Promise((resolve) => setTimeout(resolve, ms));
+}
+
+/**
+ * Gets a promise that resolves with the reason once the signal is aborted
+ * along with a function to remove the listener when no longer needed.
+ */
+export function wait_for_abort(signal) {
+  if (signal.aborted) {
+    return { promise: Promise.resolve(signal.reason), dispose() {} };
+  }
+  let onAbort;
+  const promise = new Promise((resolve) => {
+    onAbort = () => resolve(signal.reason);
+    signal.addEventListener("abort", onAbort, { once: true });
+  });
+  return {
+    promise,
+    dispose() {
+      signal.removeEventListener("abort", onAbort);
+    },
+  };
+}
+
+/**
+ * Clients of each workspace keyed by their config so they're reused
+ * across requests.
+ */
+const clientCertHttpClients = new Map();
+
+function getClientCertHttpClient(clientsId, config) {
+  let clients = clientCertHttpClients.get(clientsId);
+  if (clients == null) {
+    clients = new Map();
+    clientCertHttpClients.set(clientsId, clients);
+  }
+  const key = JSON.stringify([
+    config.certfile,
+    config.keyfile,
+    config.cafile,
+    config.ca,
+  ]);
+  let client = clients.get(key);
+  if (client == null) {
+    client = createClientCertHttpClient(config);
+    clients.set(key, client);
+    // don't cache failures so a fixed config can be picked up on retry
+    client.catch(() => clients.delete(key));
+  }
+  return client;
+}
+
+/** Closes the clients of a workspace once it's no longer used. */
+export function close_client_cert_http_clients(clientsId) {
+  const clients = clientCertHttpClients.get(clientsId);
+  if (clients == null) {
+    return;
+  }
+  clientCertHttpClients.delete(clientsId);
+  for (const client of clients.values()) {
+    client.then((client) => {
+      if (client?.kind === "node") {
+        client.agent.destroy();
+      } else {
+        client?.client.close();
+      }
+    }, () => {});
   }
 }
 
 async function createClientCertHttpClient(config) {
   if (typeof Deno !== "undefined" && Deno.createHttpClient) {
-    const certChain = await Deno.readTextFile(config.certfile);
-    const privateKey = await Deno.readTextFile(config.keyfile);
-    return Deno.createHttpClient({ certChain, privateKey });
+    const options = {};
+    if (config.certfile && config.keyfile) {
+      options.certChain = await Deno.readTextFile(config.certfile);
+      options.privateKey = await Deno.readTextFile(config.keyfile);
+    }
+    const caCerts = await readCaCerts(
+      config,
+      (path) => Deno.readTextFile(path),
+    );
+    if (caCerts.length > 0) {
+      options.caCerts = caCerts;
+    }
+    return { kind: "deno", client: Deno.createHttpClient(options) };
   }
+  if (globalThis.process?.versions?.node) {
+    const { readFile } = await import("node:fs/promises");
+    const { Agent } = await import("node:https");
+    const readTextFile = (path) => readFile(path, "utf8");
+    const options = { keepAlive: true };
+    if (config.certfile && config.keyfile) {
+      options.cert = await readTextFile(config.certfile);
+      options.key = await readTextFile(config.keyfile);
+    }
+    const caCerts = await readCaCerts(config, readTextFile);
+    if (caCerts.length > 0) {
+      const { rootCertificates } = await import("node:tls");
+      // providing `ca` replaces the default root certificates in node
+      options.ca = [...rootCertificates, ...caCerts];
+    }
+    return { kind: "node", agent: new Agent(options) };
+  }
+  console.debug(
+    "Client certificates and CA config are not supported in this runtime.",
+  );
   return undefined;
 }
+
+async function readCaCerts(config, readTextFile) {
+  const caCerts = [];
+  if (config.cafile) {
+    caCerts.push(await readTextFile(config.cafile));
+  }
+  if (config.ca) {
+    caCerts.push(config.ca);
+  }
+  return caCerts;
+}
+
+async function nodeHttpsRequest(specifier, headers, agent, signal) {
+  const url = new URL(specifier);
+  if (url.protocol !== "https:") {
+    return await fetchSpecifier(specifier, headers, undefined, signal);
+  }
+  const { request } = await import("node:https");
+  return await new Promise((resolve, reject) => {
+    const req = request(url, { agent, headers, signal }, (res) => {
+      const chunks = [];
+      res.on("data", (chunk) => chunks.push(chunk));
+      res.on("error", reject);
+      res.on("end", () => {
+        const responseHeaders = new Headers();
+        for (const [name, value] of Object.entries(res.headers)) {
+          for (const item of Array.isArray(value) ? value : [value]) {
+            responseHeaders.append(name, item);
+          }
+        }
+        resolve({
+          status: res.statusCode,
+          body: new Uint8Array(Buffer.concat(chunks)),
+          headers: responseHeaders,
+        });
+      });
+    });
+    req.on("error", reject);
+    req.end();
+  });
+}
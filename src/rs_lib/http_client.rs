@@ -1,3 +1,10 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+
+use anyhow::bail;
 use deno_cache_dir::file_fetcher::HeaderMap;
 use deno_cache_dir::file_fetcher::HeaderName;
 use deno_cache_dir::file_fetcher::HeaderValue;
@@ -11,30 +18,48 @@ use js_sys::Array;
 use js_sys::Object;
 use js_sys::Reflect;
 use serde::Deserialize;
+use tokio::sync::OnceCell;
+use tokio::sync::Semaphore;
 use url::Url;
 use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
 use wasm_bindgen::prelude::wasm_bindgen;
 
+use crate::abort::SyncAbortSignal;
+use crate::abort::with_abort_signal;
+use crate::auth_tokens::AuthTokens;
+use crate::js_function::SyncJsFunction;
+use crate::permissions::Permissions;
+use crate::progress::ProgressEvent;
+use crate::progress::ProgressReporter;
+
 #[wasm_bindgen(module = "/helpers.js")]
 extern "C" {
   async fn fetch_specifier(
     specifier: String,
     headers: JsValue,
     client_cert_config: JsValue,
+    clients_id: u32,
+    custom_fetch: JsValue,
+    timeout_ms: Option<u32>,
+    abort_signal: JsValue,
   ) -> JsValue;
+  async fn sleep(ms: u32) -> JsValue;
+  fn close_client_cert_http_clients(clients_id: u32);
 }
 
+#[derive(Debug, Clone)]
 enum FetchResult {
   Response(Response),
   Error(FetchError),
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct FetchError {
   pub error: String,
 }
 
+#[derive(Debug, Clone)]
 struct Response {
   pub status: u16,
   pub body: Vec<u8>,
@@ -45,10 +70,26 @@ async fn fetch_specifier_typed(
   specifier: &str,
   headers: Vec<(String, String)>,
   client_cert_config: JsValue,
+  clients: &ClientCertHttpClients,
+  custom_fetch: Option<&SyncJsFunction>,
+  timeout_ms: Option<u32>,
+  abort_signal: Option<&SyncAbortSignal>,
 ) -> Result<FetchResult, anyhow::Error> {
   let headers = headers_to_js_object(&headers);
-  let response =
-    fetch_specifier(specifier.to_string(), headers, client_cert_config).await;
+  let response = fetch_specifier(
+    specifier.to_string(),
+    headers,
+    client_cert_config,
+    clients.id,
+    custom_fetch
+      .map(|f| f.as_js_value().clone())
+      .unwrap_or(JsValue::UNDEFINED),
+    timeout_ms,
+    abort_signal
+      .map(|s| s.as_js_value().clone())
+      .unwrap_or(JsValue::UNDEFINED),
+  )
+  .await;
   parse_fetch_result(response).map_err(|err| {
     if let Some(s) = err.as_string() {
       anyhow::anyhow!(s)
@@ -59,9 +100,252 @@ async fn fetch_specifier_typed(
   })
 }
 
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryOptions {
+  /// Number of times to retry after the first attempt.
+  pub max_retries: u32,
+  /// Delay before the first retry, which doubles on each retry.
+  pub initial_delay_ms: u32,
+  pub max_delay_ms: u32,
+}
+
+impl Default for RetryOptions {
+  fn default() -> Self {
+    Self {
+      max_retries: 3,
+      initial_delay_ms: 250,
+      max_delay_ms: 10_000,
+    }
+  }
+}
+
+impl RetryOptions {
+  fn backoff_delay_ms(&self, attempt: u32) -> u32 {
+    let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+    self
+      .initial_delay_ms
+      .saturating_mul(factor)
+      .min(self.max_delay_ms)
+  }
+}
+
+type InFlightFetch = Arc<OnceCell<Result<FetchResult, Arc<anyhow::Error>>>>;
+
+/// Limits the number of concurrent downloads and shares the result of
+/// identical requests that are in flight.
+#[derive(Debug, Default)]
+pub struct DownloadQueue {
+  semaphore: Option<Semaphore>,
+  in_flight: Mutex<HashMap<String, InFlightFetch>>,
+}
+
+impl DownloadQueue {
+  pub fn new(max_concurrent_downloads: Option<usize>) -> Self {
+    Self {
+      semaphore: max_concurrent_downloads
+        .map(|permits| Semaphore::new(permits.max(1))),
+      in_flight: Default::default(),
+    }
+  }
+}
+
+/// Identifies the JS clients created for client certificate configs
+/// so they can be closed once the workspace is no longer used.
+#[derive(Debug)]
+pub struct ClientCertHttpClients {
+  id: u32,
+}
+
+impl Default for ClientCertHttpClients {
+  fn default() -> Self {
+    static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+    Self {
+      id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+    }
+  }
+}
+
+impl Drop for ClientCertHttpClients {
+  fn drop(&mut self) {
+    close_client_cert_http_clients(self.id);
+  }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct WasmHttpClient {
   pub cached_only: bool,
+  /// Function to use instead of the global `fetch`.
+  pub fetch: Option<SyncJsFunction>,
+  pub retry: RetryOptions,
+  pub auth_tokens: AuthTokens,
+  pub reporter: ProgressReporter,
+  pub download_queue: Arc<DownloadQueue>,
+  /// Time to wait for a response before aborting the request.
+  pub request_timeout_ms: Option<u32>,
+  /// Checked for every request, including redirects and npm downloads.
+  pub permissions: Arc<Permissions>,
+  /// Signal of the operation the requests are made for.
+  pub abort_signal: Option<SyncAbortSignal>,
+  /// Closed when the workspace and all its loaders are dropped.
+  pub client_cert_http_clients: Arc<ClientCertHttpClients>,
+}
+
+impl WasmHttpClient {
+  /// Gets a client whose requests are aborted with the provided signal.
+  pub fn with_abort_signal(&self, signal: SyncAbortSignal) -> Self {
+    Self {
+      abort_signal: Some(signal),
+      ..self.clone()
+    }
+  }
+
+  async fn fetch_shared(
+    &self,
+    url: &Url,
+    headers: Vec<(String, String)>,
+    client_cert_config: JsValue,
+  ) -> Result<FetchResult, anyhow::Error> {
+    self.permissions.check_specifier(url)?;
+    let key = in_flight_key(url, &headers);
+    let cell = self
+      .download_queue
+      .in_flight
+      .lock()
+      .unwrap()
+      .entry(key.clone())
+      .or_default()
+      .clone();
+    let wait = async {
+      cell
+        .get_or_try_init(|| async move {
+          let result = self
+            .fetch_with_progress(url, headers, client_cert_config)
+            .await
+            .map_err(Arc::new);
+          match &self.abort_signal {
+            // don't share the result of an aborted request, which makes
+            // the other callers waiting on it do the request instead
+            Some(signal) if signal.is_aborted() => Err(signal.reason()),
+            _ => Ok(result),
+          }
+        })
+        .await
+        .cloned()
+    };
+    let result = match &self.abort_signal {
+      // only stop waiting so the request continues for the other callers
+      Some(signal) => with_abort_signal(signal.as_js_value(), wait).await,
+      None => wait.await,
+    };
+    if cell.initialized() {
+      let mut in_flight = self.download_queue.in_flight.lock().unwrap();
+      // only remove the entry if it wasn't replaced by a newer request
+      if in_flight.get(&key).is_some_and(|c| Arc::ptr_eq(c, &cell)) {
+        in_flight.remove(&key);
+      }
+    }
+    match result {
+      Ok(result) => result.map_err(|err| anyhow::anyhow!("{:#}", err)),
+      Err(_) => bail!("Downloading {} was aborted.", url),
+    }
+  }
+
+  async fn fetch_with_progress(
+    &self,
+    url: &Url,
+    headers: Vec<(String, String)>,
+    client_cert_config: JsValue,
+  ) -> Result<FetchResult, anyhow::Error> {
+    self
+      .reporter
+      .report(ProgressEvent::DownloadStart { url: url.as_str() });
+    let result = self
+      .fetch_with_retries(url, headers, client_cert_config)
+      .await;
+    let url = url.as_str();
+    match &result {
+      Ok(FetchResult::Response(response)) if response.status == 304 => {
+        self.reporter.report(ProgressEvent::CacheHit { url });
+      }
+      Ok(FetchResult::Response(response)) => {
+        self.reporter.report(ProgressEvent::DownloadEnd {
+          url,
+          status: response.status,
+          bytes: response.body.len(),
+        });
+      }
+      Ok(FetchResult::Error(err)) => {
+        self.reporter.report(ProgressEvent::DownloadFailed {
+          url,
+          message: &err.error,
+        });
+      }
+      Err(err) => {
+        self.reporter.report(ProgressEvent::DownloadFailed {
+          url,
+          message: &err.to_string(),
+        });
+      }
+    }
+    result
+  }
+
+  async fn fetch_with_retries(
+    &self,
+    url: &Url,
+    headers: Vec<(String, String)>,
+    client_cert_config: JsValue,
+  ) -> Result<FetchResult, anyhow::Error> {
+    let mut attempt = 0;
+    loop {
+      attempt += 1;
+      log::debug!("Downloading {} (attempt {})", url, attempt);
+      let result = {
+        let _permit = match &self.download_queue.semaphore {
+          Some(semaphore) => Some(semaphore.acquire().await?),
+          None => None,
+        };
+        fetch_specifier_typed(
+          url.as_str(),
+          headers.clone(),
+          client_cert_config.clone(),
+          &self.client_cert_http_clients,
+          self.fetch.as_ref(),
+          self.request_timeout_ms,
+          self.abort_signal.as_ref(),
+        )
+        .await?
+      };
+      let (reason, retry_after_ms) = match &result {
+        FetchResult::Response(response)
+          if response.status == 429 || response.status >= 500 =>
+        {
+          (
+            format!("status {}", response.status),
+            retry_after_ms(&response.headers),
+          )
+        }
+        FetchResult::Response(_) => return Ok(result),
+        FetchResult::Error(err) => (err.error.clone(), None),
+      };
+      if attempt > self.retry.max_retries
+        || self.abort_signal.as_ref().is_some_and(|s| s.is_aborted())
+      {
+        return Ok(result);
+      }
+      let delay_ms = retry_after_ms
+        .unwrap_or_else(|| self.retry.backoff_delay_ms(attempt))
+        .min(self.retry.max_delay_ms);
+      log::debug!(
+        "Failed downloading {} ({}). Retrying in {}ms.",
+        url,
+        reason,
+        delay_ms
+      );
+      _ = sleep(delay_ms).await;
+    }
+  }
 }
 
 #[async_trait::async_trait(?Send)]
@@ -76,11 +360,20 @@ impl deno_cache_dir::file_fetcher::HttpClient for WasmHttpClient {
         "Cannot download because --cached-only was specified.",
       ))));
     }
-    let headers = headers
+    let mut headers = headers
       .into_iter()
       .filter_map(|(k, v)| Some((k?.to_string(), v.to_str().ok()?.to_string())))
       .collect::<Vec<(String, String)>>();
-    let result = fetch_specifier_typed(url.as_str(), headers, JsValue::NULL)
+    if !headers
+      .iter()
+      .any(|(k, _)| k.eq_ignore_ascii_case("authorization"))
+      && let Some(auth) = self.auth_tokens.header_value_for(url)
+    {
+      headers.push(("authorization".to_string(), auth));
+    }
+    self.reporter.record_download(url.as_str());
+    let result = self
+      .fetch_shared(url, headers, JsValue::NULL)
       .await
       .map_err(|err| {
         SendError::Failed(Box::new(std::io::Error::other(err.to_string())))
@@ -105,7 +398,6 @@ impl deno_cache_dir::file_fetcher::HttpClient for WasmHttpClient {
 
 #[async_trait::async_trait(?Send)]
 impl deno_npm_cache::NpmCacheHttpClient for WasmHttpClient {
-  // todo: implement retrying
   async fn download_with_retries_on_any_tokio_runtime(
     &self,
     url: Url,
@@ -121,14 +413,17 @@ impl deno_npm_cache::NpmCacheHttpClient for WasmHttpClient {
       headers.push(("if-none-match".to_string(), etag));
     }
 
+    if url.path().ends_with(".tgz") {
+      self.reporter.record_download(url.as_str());
+    }
     let client_cert_config = registry_config_to_js(maybe_registry_config);
-    let result =
-      fetch_specifier_typed(url.as_str(), headers, client_cert_config)
-        .await
-        .map_err(|err| deno_npm_cache::DownloadError {
-          status_code: None,
-          error: JsErrorBox::generic(err.to_string()),
-        })?;
+    let result = self
+      .fetch_shared(&url, headers, client_cert_config)
+      .await
+      .map_err(|err| deno_npm_cache::DownloadError {
+        status_code: None,
+        error: JsErrorBox::generic(err.to_string()),
+      })?;
 
     let response = match result {
       FetchResult::Response(res) => res,
@@ -167,29 +462,47 @@ impl deno_npm_cache::NpmCacheHttpClient for WasmHttpClient {
   }
 }
 
+fn in_flight_key(url: &Url, headers: &[(String, String)]) -> String {
+  let mut headers = headers
+    .iter()
+    .map(|(k, v)| format!("{}: {}", k.to_ascii_lowercase(), v))
+    .collect::<Vec<_>>();
+  headers.sort();
+  format!("{}\n{}", url, headers.join("\n"))
+}
+
+/// Parses a `Retry-After` header that's in seconds.
+fn retry_after_ms(headers: &HeaderMap) -> Option<u32> {
+  let value = headers.get("retry-after")?.to_str().ok()?;
+  let seconds = value.trim().parse::<u32>().ok()?;
+  Some(seconds.saturating_mul(1000))
+}
+
 fn registry_config_to_js(
   maybe_registry_config: Option<&RegistryConfig>,
 ) -> JsValue {
   let Some(config) = maybe_registry_config else {
     return JsValue::NULL;
   };
-  let (Some(certfile), Some(keyfile)) = (&config.certfile, &config.keyfile)
-  else {
+  let mut entries = Vec::new();
+  if let (Some(certfile), Some(keyfile)) = (&config.certfile, &config.keyfile) {
+    entries.push(("certfile", certfile));
+    entries.push(("keyfile", keyfile));
+  }
+  if let Some(cafile) = &config.cafile {
+    entries.push(("cafile", cafile));
+  }
+  if let Some(ca) = &config.ca {
+    entries.push(("ca", ca));
+  }
+  if entries.is_empty() {
     return JsValue::NULL;
-  };
+  }
   let obj = Object::new();
-  Reflect::set(
-    &obj,
-    &JsValue::from_str("certfile"),
-    &JsValue::from_str(certfile),
-  )
-  .unwrap();
-  Reflect::set(
-    &obj,
-    &JsValue::from_str("keyfile"),
-    &JsValue::from_str(keyfile),
-  )
-  .unwrap();
+  for (key, value) in entries {
+    Reflect::set(&obj, &JsValue::from_str(key), &JsValue::from_str(value))
+      .unwrap();
+  }
   obj.into()
 }
 
@@ -0,0 +1,37 @@
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+
+/// A JavaScript function that can be stored in the types that
+/// the Deno crates require to be `Send + Sync`.
+#[derive(Debug, Clone)]
+pub struct SyncJsFunction(js_sys::Function);
+
+// SAFETY: Wasm is single threaded, so the function is never accessed
+// from another thread.
+unsafe impl Send for SyncJsFunction {}
+unsafe impl Sync for SyncJsFunction {}
+
+impl SyncJsFunction {
+  /// Gets the function from a value provided in the options, erroring
+  /// when something other than a function was provided.
+  pub fn from_option(
+    value: JsValue,
+    option_name: &str,
+  ) -> Result<Option<Self>, anyhow::Error> {
+    if value.is_undefined() || value.is_null() {
+      return Ok(None);
+    }
+    match value.dyn_into::<js_sys::Function>() {
+      Ok(func) => Ok(Some(Self(func))),
+      Err(_) => anyhow::bail!("The {} option must be a function.", option_name),
+    }
+  }
+
+  pub fn call1(&self, arg: &JsValue) -> Result<JsValue, JsValue> {
+    self.0.call1(&JsValue::UNDEFINED, arg)
+  }
+
+  pub fn as_js_value(&self) -> &JsValue {
+    &self.0
+  }
+}
@@ -1,7 +1,16 @@
+mod abort;
+mod auth_tokens;
+mod cjs_analysis_cache;
+mod diagnostics;
 mod http_client;
+mod js_function;
+mod permissions;
+mod progress;
 
 use std::borrow::Cow;
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::error::Error;
 use std::path::Path;
 use std::path::PathBuf;
@@ -11,15 +20,22 @@ use std::sync::OnceLock;
 
 use anyhow::Context;
 use anyhow::bail;
+use base64::Engine as _;
 use deno_ast::ModuleKind;
 use deno_cache_dir::file_fetcher::CacheSetting;
+use deno_cache_dir::file_fetcher::File;
+use deno_cache_dir::file_fetcher::LoadedFrom;
 use deno_cache_dir::file_fetcher::NullBlobStore;
 use deno_config::deno_json::NewestDependencyDate;
+use deno_config::deno_json::NodeModulesDirMode;
+use deno_config::workspace::SpecifiedImportMap;
 use deno_error::JsErrorBox;
+use deno_error::JsErrorClass;
 use deno_graph::CheckJsOption;
 use deno_graph::GraphKind;
 use deno_graph::JsrMetadataStore;
 use deno_graph::MediaType;
+use deno_graph::Module;
 use deno_graph::ModuleGraph;
 use deno_graph::Position;
 use deno_graph::WalkOptions;
@@ -30,12 +46,14 @@ use deno_graph::ast::EsParser;
 use deno_graph::ast::ParsedSourceStore;
 use deno_npm_installer::NpmInstallerFactory;
 use deno_npm_installer::NpmInstallerFactoryOptions;
-use deno_npm_installer::Reporter;
+use deno_npm_installer::PackageCaching;
 use deno_npm_installer::lifecycle_scripts::NullLifecycleScriptsExecutor;
 use deno_resolver::DenoResolveError;
 use deno_resolver::DenoResolveErrorKind;
+use deno_resolver::MappedResolutionDiagnosticWithPosition;
 use deno_resolver::cache::ParsedSourceCache;
 use deno_resolver::cjs::CjsTrackerRc;
+use deno_resolver::cjs::IsCjsResolutionMode;
 use deno_resolver::deno_json::CompilerOptionsOverrides;
 use deno_resolver::deno_json::CompilerOptionsResolver;
 use deno_resolver::deno_json::JsxImportSourceConfigResolver;
@@ -44,10 +62,15 @@ use deno_resolver::factory::ConfigDiscoveryOption;
 use deno_resolver::factory::NpmSystemInfo;
 use deno_resolver::factory::ResolverFactory;
 use deno_resolver::factory::ResolverFactoryOptions;
+use deno_resolver::factory::SpecifiedImportMapProvider;
 use deno_resolver::factory::WorkspaceFactory;
 use deno_resolver::factory::WorkspaceFactoryOptions;
+use deno_resolver::file_fetcher::CheckSpecifierKind;
 use deno_resolver::file_fetcher::DenoGraphLoader;
 use deno_resolver::file_fetcher::DenoGraphLoaderOptions;
+use deno_resolver::file_fetcher::FetchError;
+use deno_resolver::file_fetcher::FetchOptions;
+use deno_resolver::file_fetcher::FetchPermissionsOptionRef;
 use deno_resolver::file_fetcher::PermissionedFileFetcher;
 use deno_resolver::file_fetcher::PermissionedFileFetcherOptions;
 use deno_resolver::graph::DefaultDenoResolverRc;
@@ -65,7 +88,7 @@ use deno_resolver::workspace::MappedResolutionError;
 use deno_semver::SmallStackString;
 use deno_semver::jsr::JsrPackageReqReference;
 use deno_semver::npm::NpmPackageReqReference;
-use base64::Engine as _;
+use deno_semver::package::PackageNv;
 use js_sys::Object;
 use js_sys::Uint8Array;
 use log::LevelFilter;
@@ -81,12 +104,32 @@ use node_resolver::errors::NodeJsErrorCoded;
 use serde::Deserialize;
 use serde::Serialize;
 use sys_traits::EnvCurrentDir;
+use sys_traits::FsRead;
 use sys_traits::impls::RealSys;
 use url::Url;
 use wasm_bindgen::JsValue;
 use wasm_bindgen::prelude::wasm_bindgen;
 
+use self::abort::SyncAbortSignal;
+use self::abort::with_abort_signal;
+use self::auth_tokens::AuthTokens;
+use self::cjs_analysis_cache::CjsAnalysisCache;
+use self::diagnostics::EntrypointDiagnostic;
+use self::diagnostics::PendingResolutionDiagnostics;
+use self::diagnostics::ResolutionDiagnostic;
+use self::diagnostics::ResolutionDiagnostics;
+use self::http_client::DownloadQueue;
+use self::http_client::RetryOptions;
 use self::http_client::WasmHttpClient;
+use self::js_function::SyncJsFunction;
+use self::permissions::ACCESS_DENIED_CLASS;
+use self::permissions::ACCESS_DENIED_CODE;
+use self::permissions::PermissionDeniedError;
+use self::permissions::PermissionedGraphLoader;
+use self::permissions::Permissions;
+use self::permissions::PermissionsOptions;
+use self::progress::ProgressEvent;
+use self::progress::ProgressReporter;
 
 #[wasm_bindgen]
 extern "C" {
@@ -121,32 +164,6 @@ impl log::Log for Logger {
   fn flush(&self) {}
 }
 
-#[derive(Debug, Clone)]
-pub struct ConsoleLogReporter;
-
-impl Reporter for ConsoleLogReporter {
-  type Guard = ();
-  type ClearGuard = ();
-
-  fn on_blocking(&self, message: &str) -> Self::Guard {
-    error(&JsValue::from(format!(
-      "{} {}",
-      "Blocking", // todo: cyan
-      message
-    )));
-  }
-
-  fn on_initializing(&self, message: &str) -> Self::Guard {
-    error(&JsValue::from(format!(
-      "{} {}",
-      "Initialize", // todo: green
-      message
-    )));
-  }
-
-  fn clear_guard(&self) -> Self::ClearGuard {}
-}
-
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LoadResponse {
@@ -164,28 +181,90 @@ pub struct DenoWorkspaceOptions {
   #[serde(default)]
   pub no_lock: Option<bool>,
   #[serde(default)]
+  pub frozen_lockfile: Option<bool>,
+  #[serde(default)]
   pub platform: Option<String>,
   #[serde(default)]
   pub config_path: Option<String>,
   #[serde(default)]
+  pub cache_dir: Option<String>,
+  #[serde(default)]
+  pub node_modules_dir: Option<String>,
+  #[serde(default)]
+  pub vendor: Option<bool>,
+  #[serde(default)]
+  pub import_map: Option<serde_json::Value>,
+  #[serde(default)]
+  pub cjs_detection: Option<String>,
+  #[serde(default)]
+  pub translate_cjs: Option<bool>,
+  #[serde(default)]
   pub node_conditions: Option<Vec<String>>,
   #[serde(default)]
+  pub import_conditions: Option<Vec<String>>,
+  #[serde(default)]
+  pub require_conditions: Option<Vec<String>>,
+  #[serde(default)]
   pub newest_dependency_date: Option<chrono::DateTime<chrono::Utc>>,
   #[serde(default)]
+  pub cache_setting: Option<CacheSettingOption>,
+  #[serde(default)]
   pub cached_only: Option<bool>,
   #[serde(default)]
   pub preserve_jsx: Option<bool>,
   #[serde(default)]
   pub no_transpile: Option<bool>,
   #[serde(default)]
+  pub include_types: Option<bool>,
+  #[serde(default, with = "serde_wasm_bindgen::preserve")]
+  pub fetch: JsValue,
+  #[serde(default)]
+  pub retry: Option<RetryOptions>,
+  #[serde(default)]
+  pub auth_tokens: Option<String>,
+  #[serde(default)]
+  pub max_concurrent_downloads: Option<usize>,
+  #[serde(default)]
+  pub request_timeout: Option<u32>,
+  #[serde(default)]
+  pub permissions: Option<PermissionsOptions>,
+  #[serde(default)]
+  pub source_map: Option<String>,
+  #[serde(default, with = "serde_wasm_bindgen::preserve")]
+  pub on_progress: JsValue,
+  #[serde(default)]
+  pub quiet: Option<bool>,
+  #[serde(default)]
   pub debug: Option<bool>,
 }
 
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum CacheSettingOption {
+  Named(String),
+  ReloadPrefixes(Vec<String>),
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddEntrypointsOptions {
+  #[serde(default)]
+  pub follow_dynamic_imports: Option<bool>,
+  #[serde(default, with = "serde_wasm_bindgen::preserve")]
+  pub signal: JsValue,
+}
+
 #[wasm_bindgen]
 pub struct DenoWorkspace {
+  graph_kind: GraphKind,
+  cache_setting: CacheSetting,
+  source_map_mode: SourceMapMode,
+  permissions: Arc<Permissions>,
   http_client: WasmHttpClient,
+  progress_reporter: ProgressReporter,
+  pending_resolution_diagnostics: PendingResolutionDiagnostics,
   npm_installer_factory:
-    Rc<NpmInstallerFactory<WasmHttpClient, ConsoleLogReporter, RealSys>>,
+    Rc<NpmInstallerFactory<WasmHttpClient, ProgressReporter, RealSys>>,
   resolver_factory: Arc<ResolverFactory<RealSys>>,
   workspace_factory: Arc<WorkspaceFactory<RealSys>>,
 }
@@ -222,6 +301,58 @@ impl DenoWorkspace {
       })
     }
 
+    fn resolve_node_modules_dir_mode(
+      options: &DenoWorkspaceOptions,
+    ) -> Result<Option<NodeModulesDirMode>, anyhow::Error> {
+      Ok(match options.node_modules_dir.as_deref() {
+        Some("none") => Some(NodeModulesDirMode::None),
+        Some("auto") => Some(NodeModulesDirMode::Auto),
+        Some("manual") => Some(NodeModulesDirMode::Manual),
+        Some(value) => bail!("Unknown node_modules dir mode '{}'", value),
+        None => None,
+      })
+    }
+
+    fn resolve_is_cjs_resolution_mode(
+      options: &DenoWorkspaceOptions,
+    ) -> Result<IsCjsResolutionMode, anyhow::Error> {
+      Ok(match options.cjs_detection.as_deref() {
+        Some("explicit") => IsCjsResolutionMode::ExplicitTypeCommonJs,
+        Some("implicit") => IsCjsResolutionMode::ImplicitTypeCommonJs,
+        Some("disabled") => IsCjsResolutionMode::Disabled,
+        Some(value) => bail!("Unknown CommonJS detection mode '{}'", value),
+        None => IsCjsResolutionMode::ExplicitTypeCommonJs,
+      })
+    }
+
+    fn resolve_cache_setting(
+      options: &DenoWorkspaceOptions,
+    ) -> Result<CacheSetting, anyhow::Error> {
+      Ok(match &options.cache_setting {
+        Some(CacheSettingOption::Named(value)) => match value.as_str() {
+          "use" => CacheSetting::Use,
+          "only" => CacheSetting::Only,
+          "reload" => CacheSetting::ReloadAll,
+          _ => bail!("Unknown cache setting '{}'", value),
+        },
+        Some(CacheSettingOption::ReloadPrefixes(prefixes)) => {
+          CacheSetting::ReloadSome(prefixes.clone())
+        }
+        None if options.cached_only.unwrap_or_default() => CacheSetting::Only,
+        None => CacheSetting::Use,
+      })
+    }
+
+    fn resolve_source_map_mode(
+      options: &DenoWorkspaceOptions,
+    ) -> Result<SourceMapMode, anyhow::Error> {
+      Ok(match options.source_map.as_deref() {
+        Some("inline") | None => SourceMapMode::Inline,
+        Some("external") => SourceMapMode::External,
+        Some(value) => bail!("Unknown source map mode '{}'", value),
+      })
+    }
+
     let debug = options.debug.unwrap_or(false);
     let logger = GLOBAL_LOGGER.get_or_init(|| Logger { debug });
     _ = log::set_logger(logger).map(|()| {
@@ -235,6 +366,11 @@ impl DenoWorkspace {
     let sys = RealSys;
     let cwd = sys.env_current_dir()?;
     let is_browser_platform = resolve_is_browser_platform(&options)?;
+    let node_modules_dir = resolve_node_modules_dir_mode(&options)?;
+    let is_cjs_resolution_mode = resolve_is_cjs_resolution_mode(&options)?;
+    let translate_cjs = options.translate_cjs.unwrap_or(false);
+    let cache_setting = resolve_cache_setting(&options)?;
+    let source_map_mode = resolve_source_map_mode(&options)?;
     let config_discovery = if options.no_config.unwrap_or_default() {
       ConfigDiscoveryOption::Disabled
     } else if let Some(config_path) = options.config_path {
@@ -245,6 +381,15 @@ impl DenoWorkspace {
     } else {
       ConfigDiscoveryOption::DiscoverCwd
     };
+    let maybe_custom_deno_dir_root = options
+      .cache_dir
+      .map(|cache_dir| resolve_absolute_path(cache_dir, &cwd))
+      .transpose()
+      .context("Failed resolving cache directory.")?;
+    // only persist analyses when the user opted into a cache directory
+    let cjs_analysis_cache_dir = maybe_custom_deno_dir_root
+      .as_ref()
+      .map(|dir| dir.join("cjs_analysis_cache_v1"));
     let workspace_factory = Arc::new(WorkspaceFactory::new(
       sys.clone(),
       cwd,
@@ -252,18 +397,45 @@ impl DenoWorkspace {
         additional_config_file_names: &[],
         config_discovery,
         is_package_manager_subcommand: false,
-        frozen_lockfile: None, // provide this via config
-        lock_arg: None,        // supports the default only
+        frozen_lockfile: options.frozen_lockfile,
+        lock_arg: None, // supports the default only
         lockfile_skip_write: false,
-        maybe_custom_deno_dir_root: None,
-        node_modules_dir: None, // provide this via config
+        maybe_custom_deno_dir_root,
+        node_modules_dir,
         no_lock: options.no_lock.unwrap_or_default(),
         no_npm: false,
         npm_process_state: None,
         root_node_modules_dir_override: None,
-        vendor: None, // provide this via the config
+        vendor: options.vendor,
       },
     ));
+    let npm_dirs = std::iter::once(
+      workspace_factory.npm_cache_dir()?.root_dir().to_path_buf(),
+    )
+    .chain(
+      workspace_factory
+        .node_modules_dir_path()?
+        .map(|path| path.to_path_buf()),
+    )
+    .collect();
+    let permissions = Arc::new(
+      Permissions::from_options(
+        options.permissions.unwrap_or_default(),
+        workspace_factory.initial_cwd(),
+        npm_dirs,
+      )
+      .context("Failed resolving permissions.")?,
+    );
+    let pending_resolution_diagnostics =
+      PendingResolutionDiagnostics::default();
+    let specified_import_map = options.import_map.map(|value| {
+      Box::new(ImportMapOverrideProvider {
+        sys: sys.clone(),
+        cwd: workspace_factory.initial_cwd().clone(),
+        workspace_factory: workspace_factory.clone(),
+        value,
+      }) as Box<dyn SpecifiedImportMapProvider>
+    });
     let resolver_factory = Arc::new(ResolverFactory::new(
       workspace_factory.clone(),
       ResolverFactoryOptions {
@@ -281,9 +453,7 @@ impl DenoWorkspace {
           preserve_jsx: options.preserve_jsx.unwrap_or(false),
           force_check_js: false,
         },
-        // todo: make this configurable
-        is_cjs_resolution_mode:
-          deno_resolver::cjs::IsCjsResolutionMode::ExplicitTypeCommonJs,
+        is_cjs_resolution_mode,
         unstable_sloppy_imports: true,
         npm_system_info: npm_system_info()?,
         node_resolver_options: NodeResolverOptions {
@@ -296,39 +466,97 @@ impl DenoWorkspace {
               .into_iter()
               .map(|c| c.into())
               .collect(),
-            import_conditions_override: None,
-            require_conditions_override: None,
+            import_conditions_override: options.import_conditions.map(
+              |conditions| conditions.into_iter().map(|c| c.into()).collect(),
+            ),
+            require_conditions_override: options.require_conditions.map(
+              |conditions| conditions.into_iter().map(|c| c.into()).collect(),
+            ),
           },
           typescript_version: None,
         },
-        node_analysis_cache: None,
-        node_code_translator_mode: NodeCodeTranslatorMode::Disabled,
+        node_analysis_cache: translate_cjs.then(|| {
+          Arc::new(CjsAnalysisCache::new(sys.clone(), cjs_analysis_cache_dir))
+            as _
+        }),
+        node_code_translator_mode: if translate_cjs {
+          NodeCodeTranslatorMode::Enabled
+        } else {
+          NodeCodeTranslatorMode::Disabled
+        },
         node_resolution_cache: Some(Arc::new(NodeResolutionThreadLocalCache)),
         package_json_cache: Some(Arc::new(PackageJsonThreadLocalCache)),
         package_json_dep_resolution: None,
         require_modules: Vec::new(),
-        specified_import_map: None,
+        specified_import_map,
         bare_node_builtins: true,
         newest_dependency_date: options
           .newest_dependency_date
           .map(NewestDependencyDate::Enabled),
-        // todo: report these
-        on_mapped_resolution_diagnostic: None,
+        on_mapped_resolution_diagnostic: Some(Arc::new({
+          let workspace_factory = workspace_factory.clone();
+          let pending = pending_resolution_diagnostics.clone();
+          move |diagnostic: MappedResolutionDiagnosticWithPosition| {
+            let config_file = workspace_factory
+              .workspace_directory()
+              .ok()
+              .and_then(|workspace_dir| {
+                let member_dir = workspace_dir
+                  .workspace
+                  .resolve_member_dir(&diagnostic.referrer);
+                member_dir
+                  .maybe_deno_json()
+                  .map(|config| config.specifier.clone())
+                  .or_else(|| {
+                    member_dir.maybe_pkg_json().and_then(|pkg_json| {
+                      deno_path_util::url_from_file_path(&pkg_json.path).ok()
+                    })
+                  })
+              });
+            pending.push(
+              ResolutionDiagnostic::from_mapped_resolution_diagnostic(
+                &diagnostic,
+                config_file.map(String::from),
+              ),
+            );
+          }
+        })),
       },
     ));
-    let http_client = WasmHttpClient::default();
+    let progress_reporter = ProgressReporter {
+      on_progress: SyncJsFunction::from_option(
+        options.on_progress,
+        "onProgress",
+      )?,
+      quiet: options.quiet.unwrap_or(false),
+      downloaded_urls: Default::default(),
+    };
+    let http_client = WasmHttpClient {
+      cached_only: matches!(cache_setting, CacheSetting::Only),
+      reporter: progress_reporter.clone(),
+      download_queue: Arc::new(DownloadQueue::new(
+        options.max_concurrent_downloads,
+      )),
+      request_timeout_ms: options.request_timeout,
+      permissions: permissions.clone(),
+      fetch: SyncJsFunction::from_option(options.fetch, "fetch")?,
+      retry: options.retry.unwrap_or_default(),
+      auth_tokens: match &options.auth_tokens {
+        Some(value) => AuthTokens::new(value)?,
+        None => AuthTokens::default(),
+      },
+      ..Default::default()
+    };
     let npm_installer_factory = Rc::new(NpmInstallerFactory::new(
       resolver_factory.clone(),
       Arc::new(http_client.clone()),
       Arc::new(NullLifecycleScriptsExecutor),
-      ConsoleLogReporter,
+      progress_reporter.clone(),
       None,
       NpmInstallerFactoryOptions {
-        cache_setting: if options.cached_only.unwrap_or_default() {
-          deno_npm_cache::NpmCacheSetting::Only
-        } else {
-          deno_npm_cache::NpmCacheSetting::Use
-        },
+        cache_setting: deno_npm_cache::NpmCacheSetting::from_cache_setting(
+          &cache_setting,
+        ),
         caching_strategy: deno_npm_installer::graph::NpmCachingStrategy::Eager,
         clean_on_install: false,
         lifecycle_scripts_config: deno_npm_installer::LifecycleScriptsConfig {
@@ -345,7 +573,17 @@ impl DenoWorkspace {
       },
     ));
     Ok(Self {
+      graph_kind: if options.include_types.unwrap_or(false) {
+        GraphKind::All
+      } else {
+        GraphKind::CodeOnly
+      },
+      cache_setting,
+      source_map_mode,
+      permissions,
       http_client,
+      progress_reporter,
+      pending_resolution_diagnostics,
       npm_installer_factory,
       resolver_factory,
       workspace_factory,
@@ -356,7 +594,7 @@ impl DenoWorkspace {
     self
       .create_loader_inner()
       .await
-      .map_err(|e| create_js_error(&e))
+      .map_err(|err| create_js_error(&err))
   }
 
   async fn create_loader_inner(&self) -> Result<DenoLoader, anyhow::Error> {
@@ -364,17 +602,13 @@ impl DenoWorkspace {
       .npm_installer_factory
       .initialize_npm_resolution_if_managed()
       .await?;
-    let file_fetcher = Arc::new(PermissionedFileFetcher::new(
-      NullBlobStore,
-      Arc::new(self.workspace_factory.http_cache()?.clone()),
+    let memory_files = MemoryFilesRc::default();
+    let file_fetcher = create_file_fetcher(
+      &self.workspace_factory,
       self.http_client.clone(),
-      MemoryFilesRc::default(),
-      self.workspace_factory.sys().clone(),
-      PermissionedFileFetcherOptions {
-        allow_remote: true,
-        cache_setting: CacheSetting::Use,
-      },
-    ));
+      memory_files.clone(),
+      self.cache_setting.clone(),
+    )?;
     Ok(DenoLoader {
       cjs_tracker: self.resolver_factory.cjs_tracker()?.clone(),
       compiler_options_resolver: self
@@ -382,6 +616,9 @@ impl DenoWorkspace {
         .compiler_options_resolver()?
         .clone(),
       file_fetcher,
+      http_client: self.http_client.clone(),
+      cache_setting: self.cache_setting.clone(),
+      memory_files,
       emitter: self.resolver_factory.emitter()?.clone(),
       resolver: self.resolver_factory.deno_resolver().await?.clone(),
       workspace_factory: self.workspace_factory.clone(),
@@ -390,8 +627,17 @@ impl DenoWorkspace {
       parsed_source_cache: self.resolver_factory.parsed_source_cache().clone(),
       module_loader: self.resolver_factory.module_loader()?.clone(),
       task_queue: Default::default(),
+      invalidated_roots: Default::default(),
+      installed_npm_packages: Default::default(),
+      graph_kind: self.graph_kind,
+      source_map_mode: self.source_map_mode,
+      permissions: self.permissions.clone(),
+      resolution_diagnostics: ResolutionDiagnostics::new(
+        self.pending_resolution_diagnostics.clone(),
+      ),
+      progress_reporter: self.progress_reporter.clone(),
       graph: ModuleGraphCell::new(deno_graph::ModuleGraph::new(
-        deno_graph::GraphKind::CodeOnly,
+        self.graph_kind,
       )),
       jsr_metadata_store: Rc::new(JsrMetadataStore::default()),
     })
@@ -403,17 +649,30 @@ pub struct DenoLoader {
   cjs_tracker: CjsTrackerRc<DenoInNpmPackageChecker, RealSys>,
   compiler_options_resolver: Arc<CompilerOptionsResolver>,
   resolver: DefaultDenoResolverRc<RealSys>,
-  file_fetcher:
-    Arc<PermissionedFileFetcher<NullBlobStore, RealSys, WasmHttpClient>>,
+  file_fetcher: Arc<WasmFileFetcher>,
+  http_client: WasmHttpClient,
+  cache_setting: CacheSetting,
+  memory_files: MemoryFilesRc,
   emitter: Arc<Emitter<DenoInNpmPackageChecker, RealSys>>,
   npm_installer_factory:
-    Rc<NpmInstallerFactory<WasmHttpClient, ConsoleLogReporter, RealSys>>,
+    Rc<NpmInstallerFactory<WasmHttpClient, ProgressReporter, RealSys>>,
   parsed_source_cache: Arc<ParsedSourceCache>,
   module_loader: Arc<ModuleLoader<RealSys>>,
   resolver_factory: Arc<ResolverFactory<RealSys>>,
   workspace_factory: Arc<WorkspaceFactory<RealSys>>,
+  graph_kind: GraphKind,
+  source_map_mode: SourceMapMode,
+  permissions: Arc<Permissions>,
+  resolution_diagnostics: ResolutionDiagnostics,
+  progress_reporter: ProgressReporter,
   graph: ModuleGraphCell,
   task_queue: Rc<deno_unsync::TaskQueue>,
+  /// Roots that were removed from the graph by an invalidation and
+  /// that should be rebuilt on the next graph modification.
+  invalidated_roots: RefCell<Vec<Url>>,
+  /// Npm packages installed into the npm cache or the local
+  /// node_modules folder.
+  installed_npm_packages: RefCell<HashSet<PackageNv>>,
   jsr_metadata_store: Rc<JsrMetadataStore>,
 }
 
@@ -425,57 +684,159 @@ impl Drop for DenoLoader {
 
 #[wasm_bindgen]
 impl DenoLoader {
+  pub fn take_resolution_diagnostics(&self) -> Result<JsValue, JsValue> {
+    let diagnostics = self.resolution_diagnostics.take();
+    serde_wasm_bindgen::to_value(&diagnostics).map_err(|err| {
+      create_js_error(
+        &anyhow::anyhow!("{}", err).context("Failed serializing diagnostics."),
+      )
+    })
+  }
+
   pub fn get_graph(&self) -> JsValue {
     let serializer =
       serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
     self.graph.get().serialize(&serializer).unwrap()
   }
 
-  pub async fn add_entrypoints(
+  pub fn add_memory_file(
     &self,
-    entrypoints: Vec<String>,
-  ) -> Result<Vec<String>, JsValue> {
+    specifier: String,
+    source: Vec<u8>,
+    media_type: Option<u8>,
+  ) -> Result<(), JsValue> {
     self
-      .add_entrypoints_internal(entrypoints)
-      .await
+      .add_memory_file_inner(specifier, source, media_type)
       .map_err(|e| create_js_error(&e))
   }
 
+  fn add_memory_file_inner(
+    &self,
+    specifier: String,
+    source: Vec<u8>,
+    media_type: Option<u8>,
+  ) -> Result<(), anyhow::Error> {
+    let url = self.resolve_url_or_path(specifier)?;
+    let maybe_headers = match media_type {
+      Some(media_type) => {
+        let media_type = u8_to_media_type(media_type)?;
+        let content_type = media_type_to_content_type(media_type)
+          .with_context(|| {
+            format!("Unsupported media type for in-memory file: {}", media_type)
+          })?;
+        let headers = [("content-type".to_string(), content_type.to_string())]
+          .into_iter()
+          .collect();
+        // the content type alone can't distinguish some media types
+        // (ex. Cjs or Dts), so those need a matching extension
+        let resolved_media_type =
+          MediaType::from_specifier_and_headers(&url, Some(&headers));
+        if resolved_media_type != media_type {
+          bail!(
+            "Media type {} for in-memory file '{}' requires a matching extension (ex. '{}').",
+            media_type,
+            url,
+            media_type.as_ts_extension()
+          );
+        }
+        Some(headers)
+      }
+      None => None,
+    };
+    self.parsed_source_cache.free(&url);
+    self.memory_files.insert(
+      url.clone(),
+      File {
+        url,
+        mtime: None,
+        maybe_headers,
+        source: source.into(),
+        loaded_from: LoadedFrom::Local,
+      },
+    );
+    Ok(())
+  }
+
+  pub fn remove_memory_file(&self, specifier: String) -> Result<bool, JsValue> {
+    let url = self
+      .resolve_url_or_path(specifier)
+      .map_err(|e| create_js_error(&e))?;
+    self.parsed_source_cache.free(&url);
+    Ok(self.memory_files.remove(&url).is_some())
+  }
+
+  pub async fn add_entrypoints(
+    &self,
+    entrypoints: Vec<String>,
+    options: JsValue,
+  ) -> Result<JsValue, JsValue> {
+    let options = if options.is_undefined() {
+      AddEntrypointsOptions::default()
+    } else {
+      serde_wasm_bindgen::from_value(options).map_err(|err| {
+        create_js_error(
+          &anyhow::anyhow!("{}", err)
+            .context("Failed deserializing add entrypoints options."),
+        )
+      })?
+    };
+    let signal = options.signal.clone();
+    let diagnostics = with_abort_signal(
+      &signal,
+      self.resolution_diagnostics.collect_async(async {
+        self
+          .add_entrypoints_internal(entrypoints, options)
+          .await
+          .map_err(|e| create_js_error(&e))
+      }),
+    )
+    .await?;
+    serde_wasm_bindgen::to_value(&diagnostics).map_err(|err| {
+      create_js_error(
+        &anyhow::anyhow!("{}", err).context("Failed serializing diagnostics."),
+      )
+    })
+  }
+
   async fn add_entrypoints_internal(
     &self,
     entrypoints: Vec<String>,
-  ) -> Result<Vec<String>, anyhow::Error> {
+    options: AddEntrypointsOptions,
+  ) -> Result<Vec<EntrypointDiagnostic>, anyhow::Error> {
     let urls = entrypoints
       .into_iter()
       .map(|e| {
         self.resolve_entrypoint(
           Cow::Owned(e),
           node_resolver::ResolutionMode::Import,
+          node_resolver::NodeResolutionKind::Execution,
         )
       })
       .collect::<Result<Vec<_>, _>>()?;
-    self.add_entrypoint_urls(urls.clone()).await?;
-    let errors = self
+    let file_fetcher = self.file_fetcher_for_signal(&options.signal)?;
+    self.add_entrypoint_urls(urls.clone(), file_fetcher).await?;
+    let diagnostics = self
       .graph
       .get()
       .walk(
         urls.iter(),
         WalkOptions {
           check_js: CheckJsOption::True,
-          kind: GraphKind::CodeOnly,
-          follow_dynamic: false,
+          kind: self.graph_kind,
+          follow_dynamic: options.follow_dynamic_imports.unwrap_or(false),
           prefer_fast_check_graph: false,
         },
       )
       .errors()
-      .map(|e| e.to_string_with_range())
+      .map(|e| EntrypointDiagnostic::from_graph_error(&e))
       .collect();
-    Ok(errors)
+    Ok(diagnostics)
   }
 
   async fn add_entrypoint_urls(
     &self,
     entrypoints: Vec<Url>,
+    file_fetcher: Arc<WasmFileFetcher>,
   ) -> Result<(), anyhow::Error> {
     // only allow one async task to modify the graph at a time
     let task_queue = self.task_queue.clone();
@@ -498,7 +859,7 @@ impl DenoLoader {
             .resolver
             .as_graph_resolver(&self.cjs_tracker, &jsx_config, None);
         let loader = DenoGraphLoader::new(
-          self.file_fetcher.clone(),
+          file_fetcher,
           self.workspace_factory.global_http_cache()?.clone(),
           self.resolver_factory.in_npm_package_checker()?.clone(),
           self.workspace_factory.sys().clone(),
@@ -509,6 +870,10 @@ impl DenoLoader {
             include_npm_sources: false,
           },
         );
+        let loader = PermissionedGraphLoader {
+          inner: &loader,
+          permissions: &self.permissions,
+        };
 
         let mut locker = lockfile.as_ref().map(|l| l.as_deno_graph_locker());
         let npm_resolver =
@@ -517,7 +882,17 @@ impl DenoLoader {
           store: self.parsed_source_cache.as_ref(),
           parser: &DefaultEsParser,
         };
+        // the invalidated roots are only cleared once the graph is set so
+        // that they're rebuilt on the next call when this one errors or
+        // is dropped (ex. aborted)
+        let mut entrypoints = entrypoints;
+        for root in self.invalidated_roots.borrow().iter() {
+          if !entrypoints.contains(root) {
+            entrypoints.push(root.clone());
+          }
+        }
         let mut graph = self.graph.deep_clone();
+        let npm_packages_before = graph.npm_packages.clone();
         if graph.roots.is_empty()
           && let Some(lockfile) = lockfile
         {
@@ -532,6 +907,8 @@ impl DenoLoader {
             &loader,
             deno_graph::BuildOptions {
               is_dynamic: false,
+              // always load dynamic imports so that npm and jsr specifiers
+              // in lazily loaded modules resolve like static ones
               skip_dynamic_deps: false,
               module_info_cacher: Default::default(),
               executor: Default::default(),
@@ -544,7 +921,7 @@ impl DenoLoader {
               passthrough_jsr_specifiers: false,
               module_analyzer: &module_analyzer,
               npm_resolver: Some(npm_resolver.as_ref()),
-              reporter: None,
+              reporter: Some(&self.progress_reporter),
               resolver: Some(&graph_resolver),
               unstable_bytes_imports: true,
               unstable_text_imports: true,
@@ -552,7 +929,126 @@ impl DenoLoader {
             },
           )
           .await;
+        for package in &graph.npm_packages {
+          if !npm_packages_before.contains(package) {
+            self
+              .progress_reporter
+              .report(ProgressEvent::NpmPackageResolved {
+                package: &package.to_string(),
+              });
+          }
+        }
+        if self.workspace_factory.node_modules_dir_mode()?
+          != NodeModulesDirMode::Manual
+        {
+          let new_npm_packages = {
+            let installed = self.installed_npm_packages.borrow();
+            graph
+              .npm_packages
+              .iter()
+              .filter(|package| !installed.contains(package))
+              .cloned()
+              .collect::<HashSet<_>>()
+          };
+          if !new_npm_packages.is_empty() {
+            // install the npm packages into the npm cache and, when
+            // used, the local node_modules folder
+            self
+              .npm_installer_factory
+              .npm_installer()
+              .await?
+              .cache_packages(PackageCaching::All)
+              .await?;
+            self.report_npm_package_cache_hits(&new_npm_packages);
+            self
+              .installed_npm_packages
+              .borrow_mut()
+              .extend(new_npm_packages);
+          }
+        }
+        if let Some(lockfile) = lockfile {
+          // errors when the lockfile is frozen and would have changed
+          lockfile.write_if_changed()?;
+        }
         self.graph.set(Rc::new(graph));
+        self.invalidated_roots.borrow_mut().clear();
+        Ok(())
+      })
+      .await
+  }
+
+  pub async fn invalidate(
+    &self,
+    specifiers: Vec<String>,
+  ) -> Result<(), JsValue> {
+    self
+      .invalidate_inner(specifiers)
+      .await
+      .map_err(|e| create_js_error(&e))
+  }
+
+  async fn invalidate_inner(
+    &self,
+    specifiers: Vec<String>,
+  ) -> Result<(), anyhow::Error> {
+    let urls = specifiers
+      .into_iter()
+      .map(|s| self.resolve_url_or_path(s))
+      .collect::<Result<Vec<_>, _>>()?;
+    if urls.is_empty() {
+      return Ok(());
+    }
+    // wait for any in-progress graph modification to finish
+    let task_queue = self.task_queue.clone();
+    task_queue
+      .run(async {
+        let graph = self.graph.get();
+        let package_dirs = urls
+          .iter()
+          .filter(|url| url.path().ends_with("/package.json"))
+          .filter_map(|url| url.join("./").ok())
+          .collect::<Vec<_>>();
+        let mut urls = urls;
+        if !package_dirs.is_empty() {
+          PackageJsonThreadLocalCache::clear();
+          // specifiers in the package may now resolve differently
+          urls.extend(
+            graph
+              .modules()
+              .map(|module| module.specifier())
+              .filter(|specifier| {
+                package_dirs
+                  .iter()
+                  .any(|dir| specifier.as_str().starts_with(dir.as_str()))
+              })
+              .cloned(),
+          );
+        }
+        // a changed or new file may affect how specifiers resolve
+        NodeResolutionThreadLocalCache::clear();
+
+        let invalidated = invalidated_modules(&graph, &urls);
+        for url in urls.iter().chain(invalidated.iter()) {
+          self.parsed_source_cache.free(url);
+        }
+        // Any root that reaches an invalidated module is itself
+        // invalidated, so segmenting on the remaining roots gives
+        // a graph without any of the invalidated modules.
+        let (kept_roots, invalidated_roots): (Vec<_>, Vec<_>) = graph
+          .roots
+          .iter()
+          .cloned()
+          .partition(|root| !invalidated.contains(graph.resolve(root)));
+        if invalidated_roots.is_empty() {
+          return Ok(());
+        }
+        self.graph.set(Rc::new(graph.segment(&kept_roots)));
+        let mut pending_roots = self.invalidated_roots.borrow_mut();
+        for root in invalidated_roots {
+          if !pending_roots.contains(&root) {
+            pending_roots.push(root);
+          }
+        }
         Ok(())
       })
       .await
@@ -563,16 +1059,21 @@ impl DenoLoader {
     specifier: String,
     importer: Option<String>,
     resolution_mode: u8,
+    resolution_kind: u8,
   ) -> Result<String, JsValue> {
     let importer = self
       .resolve_provided_referrer(importer)
       .map_err(|e| create_js_error(&e))?;
     self
-      .resolve_sync_inner(
-        &specifier,
-        importer.as_ref(),
-        parse_resolution_mode(resolution_mode),
-      )
+      .resolution_diagnostics
+      .collect(|| {
+        self.resolve_sync_inner(
+          &specifier,
+          importer.as_ref(),
+          parse_resolution_mode(resolution_mode),
+          parse_resolution_kind(resolution_kind),
+        )
+      })
       .map_err(|err| {
         self.create_resolve_js_error(&err, &specifier, importer.as_ref())
       })
@@ -583,20 +1084,21 @@ impl DenoLoader {
     specifier: &str,
     importer: Option<&Url>,
     resolution_mode: node_resolver::ResolutionMode,
+    resolution_kind: node_resolver::NodeResolutionKind,
   ) -> Result<String, anyhow::Error> {
     let (specifier, referrer) = self.resolve_specifier_and_referrer(
       specifier,
       importer,
       resolution_mode,
+      resolution_kind,
     )?;
-    let resolved = self.resolver.resolve_with_graph(
+    let resolved = self.resolve_with_graph(
       &self.graph.get(),
       &specifier,
       &referrer,
-      deno_graph::Position::zeroed(),
       ResolveWithGraphOptions {
         mode: resolution_mode,
-        kind: node_resolver::NodeResolutionKind::Execution,
+        kind: resolution_kind,
         maintain_npm_specifiers: false,
       },
     )?;
@@ -608,20 +1110,30 @@ impl DenoLoader {
     specifier: String,
     importer: Option<String>,
     resolution_mode: u8,
+    resolution_kind: u8,
+    signal: JsValue,
   ) -> Result<String, JsValue> {
     let importer = self
       .resolve_provided_referrer(importer)
       .map_err(|e| create_js_error(&e))?;
-    self
-      .resolve_inner(
-        &specifier,
-        importer.as_ref(),
-        parse_resolution_mode(resolution_mode),
-      )
-      .await
-      .map_err(|err| {
-        self.create_resolve_js_error(&err, &specifier, importer.as_ref())
-      })
+    with_abort_signal(
+      &signal,
+      self.resolution_diagnostics.collect_async(async {
+        self
+          .resolve_inner(
+            &specifier,
+            importer.as_ref(),
+            parse_resolution_mode(resolution_mode),
+            parse_resolution_kind(resolution_kind),
+            &signal,
+          )
+          .await
+          .map_err(|err| {
+            self.create_resolve_js_error(&err, &specifier, importer.as_ref())
+          })
+      }),
+    )
+    .await
   }
 
   async fn resolve_inner(
@@ -629,45 +1141,88 @@ impl DenoLoader {
     specifier: &str,
     importer: Option<&Url>,
     resolution_mode: node_resolver::ResolutionMode,
+    resolution_kind: node_resolver::NodeResolutionKind,
+    signal: &JsValue,
   ) -> Result<String, anyhow::Error> {
     let (specifier, referrer) = self.resolve_specifier_and_referrer(
       specifier,
       importer,
       resolution_mode,
+      resolution_kind,
     )?;
-    let resolved = self.resolver.resolve_with_graph(
+    let resolved = self.resolve_with_graph(
       &self.graph.get(),
       &specifier,
       &referrer,
-      deno_graph::Position::zeroed(),
       ResolveWithGraphOptions {
         mode: resolution_mode,
-        kind: node_resolver::NodeResolutionKind::Execution,
+        kind: resolution_kind,
         maintain_npm_specifiers: true,
       },
     )?;
     if NpmPackageReqReference::from_specifier(&resolved).is_ok()
       || JsrPackageReqReference::from_specifier(&resolved).is_ok()
     {
-      self.add_entrypoint_urls(vec![resolved.clone()]).await?;
-      self.resolve_sync_inner(&specifier, importer, resolution_mode)
+      let file_fetcher = self.file_fetcher_for_signal(signal)?;
+      self
+        .add_entrypoint_urls(vec![resolved.clone()], file_fetcher)
+        .await?;
+      self.resolve_sync_inner(
+        &specifier,
+        importer,
+        resolution_mode,
+        resolution_kind,
+      )
     } else {
       Ok(resolved.into())
     }
   }
 
+  fn resolve_with_graph(
+    &self,
+    graph: &ModuleGraph,
+    specifier: &str,
+    referrer: &Url,
+    options: ResolveWithGraphOptions,
+  ) -> Result<Url, anyhow::Error> {
+    let is_types = options.kind.is_types();
+    if is_types
+      && let Some(resolved) =
+        resolve_ts_types_directive(graph, specifier, referrer)
+    {
+      return Ok(resolved);
+    }
+    let resolved = self.resolver.resolve_with_graph(
+      graph,
+      specifier,
+      referrer,
+      deno_graph::Position::zeroed(),
+      options,
+    )?;
+    if is_types {
+      Ok(resolve_types_dependency(graph, resolved))
+    } else {
+      Ok(resolved)
+    }
+  }
+
   fn resolve_specifier_and_referrer<'a>(
     &self,
     specifier: &'a str,
     referrer: Option<&'a Url>,
     resolution_mode: node_resolver::ResolutionMode,
+    resolution_kind: node_resolver::NodeResolutionKind,
   ) -> Result<(Cow<'a, str>, Cow<'a, Url>), anyhow::Error> {
     Ok(match referrer {
       Some(referrer) => (Cow::Borrowed(specifier), Cow::Borrowed(referrer)),
       None => {
         let entrypoint = Cow::Owned(
           self
-            .resolve_entrypoint(Cow::Borrowed(specifier), resolution_mode)?
+            .resolve_entrypoint(
+              Cow::Borrowed(specifier),
+              resolution_mode,
+              resolution_kind,
+            )?
             .into(),
         );
         (
@@ -684,26 +1239,30 @@ impl DenoLoader {
     &self,
     importer: Option<String>,
   ) -> Result<Option<Url>, anyhow::Error> {
-    let importer = importer.filter(|v| !v.is_empty());
-    Ok(match importer {
-      Some(referrer)
-        if referrer.starts_with("http:")
-          || referrer.starts_with("https:")
-          || referrer.starts_with("file:") =>
-      {
-        Some(Url::parse(&referrer)?)
-      }
-      Some(referrer) => Some(deno_path_util::url_from_file_path(
-        &sys_traits::impls::wasm_string_to_path(referrer),
-      )?),
-      None => None,
-    })
+    importer
+      .filter(|v| !v.is_empty())
+      .map(|referrer| self.resolve_url_or_path(referrer))
+      .transpose()
+  }
+
+  fn resolve_url_or_path(&self, value: String) -> Result<Url, anyhow::Error> {
+    if value.starts_with("http:")
+      || value.starts_with("https:")
+      || value.starts_with("file:")
+    {
+      Ok(Url::parse(&value)?)
+    } else {
+      Ok(deno_path_util::url_from_file_path(
+        &sys_traits::impls::wasm_string_to_path(value),
+      )?)
+    }
   }
 
   pub async fn load(
     &self,
     url: String,
     requested_module_type: u8,
+    signal: JsValue,
   ) -> Result<JsValue, JsValue> {
     let requested_module_type = match requested_module_type {
       0 => RequestedModuleType::None,
@@ -717,16 +1276,26 @@ impl DenoLoader {
         )));
       }
     };
-    self
-      .load_inner(url, &requested_module_type)
-      .await
-      .map_err(|err| create_js_error(&err))
+    let file_fetcher = self
+      .file_fetcher_for_signal(&signal)
+      .map_err(|e| create_js_error(&e))?;
+    with_abort_signal(
+      &signal,
+      self.resolution_diagnostics.collect_async(async {
+        self
+          .load_inner(url, &requested_module_type, &file_fetcher)
+          .await
+          .map_err(|err| create_load_js_error(&err))
+      }),
+    )
+    .await
   }
 
   async fn load_inner(
     &self,
     url: String,
     requested_module_type: &RequestedModuleType<'_>,
+    file_fetcher: &WasmFileFetcher,
   ) -> Result<JsValue, anyhow::Error> {
     let url = Url::parse(&url)?;
 
@@ -738,6 +1307,7 @@ impl DenoLoader {
         url
       );
     }
+    self.permissions.check_specifier(&url)?;
 
     match self
       .module_loader
@@ -746,25 +1316,38 @@ impl DenoLoader {
     {
       Ok(LoadedModuleOrAsset::Module(m)) => {
         self.parsed_source_cache.free(&m.specifier);
-        Ok(create_module_response(
-          &m.specifier,
-          m.media_type,
-          m.source.as_bytes(),
-        ))
+        Ok(
+          self
+            .create_module_response(
+              &m.specifier,
+              m.media_type,
+              m.source.as_bytes(),
+              requested_module_type,
+              file_fetcher,
+            )
+            .await,
+        )
       }
       Ok(LoadedModuleOrAsset::ExternalAsset {
         specifier,
         statically_analyzable: _,
       }) => {
-        let file = self
-          .file_fetcher
-          .fetch_bypass_permissions(&specifier)
-          .await?;
+        let file = self.fetch_file(file_fetcher, &specifier).await?;
         let media_type = MediaType::from_specifier_and_headers(
           &file.url,
           file.maybe_headers.as_ref(),
         );
-        Ok(create_module_response(&file.url, media_type, &file.source))
+        Ok(
+          self
+            .create_module_response(
+              &file.url,
+              media_type,
+              &file.source,
+              requested_module_type,
+              file_fetcher,
+            )
+            .await,
+        )
       }
       Err(err) => match err.as_kind() {
         LoadCodeSourceErrorKind::LoadUnpreparedModule(_) => {
@@ -774,40 +1357,180 @@ impl DenoLoader {
               url
             )
           }
-          let file = self.file_fetcher.fetch_bypass_permissions(&url).await?;
+          let file = self.fetch_file(file_fetcher, &url).await?;
           let media_type = MediaType::from_specifier_and_headers(
             &url,
             file.maybe_headers.as_ref(),
           );
-          match requested_module_type {
-            RequestedModuleType::Text | RequestedModuleType::Bytes => {
-              Ok(create_module_response(&file.url, media_type, &file.source))
-            }
+          let source = match requested_module_type {
             RequestedModuleType::Json
             | RequestedModuleType::None
-            | RequestedModuleType::Other(_) => {
-              if media_type.is_emittable() {
-                let str = String::from_utf8_lossy(&file.source);
-                let value = str.into();
-                let source = self
-                  .maybe_transpile(&file.url, media_type, &value, None)
-                  .await?;
-                Ok(create_module_response(
-                  &file.url,
-                  media_type,
-                  source.as_bytes(),
-                ))
-              } else {
-                Ok(create_module_response(&file.url, media_type, &file.source))
-              }
+            | RequestedModuleType::Other(_)
+              if media_type.is_emittable() =>
+            {
+              let str = String::from_utf8_lossy(&file.source);
+              let value = str.into();
+              let source = self
+                .maybe_transpile(&file.url, media_type, &value, None)
+                .await?;
+              Cow::Owned(source.as_bytes().to_vec())
             }
-          }
+            _ => Cow::Borrowed(file.source.as_ref()),
+          };
+          Ok(
+            self
+              .create_module_response(
+                &file.url,
+                media_type,
+                &source,
+                requested_module_type,
+                file_fetcher,
+              )
+              .await,
+          )
         }
         _ => Err(err.into()),
       },
     }
   }
 
+  async fn create_module_response(
+    &self,
+    url: &Url,
+    media_type: MediaType,
+    source: &[u8],
+    requested_module_type: &RequestedModuleType<'_>,
+    file_fetcher: &WasmFileFetcher,
+  ) -> JsValue {
+    // text and bytes imports get the source as-is
+    let is_code = !matches!(
+      requested_module_type,
+      RequestedModuleType::Text | RequestedModuleType::Bytes
+    );
+    let strip_inline_source_map =
+      is_code && self.source_map_mode == SourceMapMode::External;
+    // also check code that isn't emittable itself because the module
+    // loader may have transformed it (ex. translated CommonJS)
+    let inline_source_map = (media_type.is_emittable()
+      || strip_inline_source_map)
+      .then(|| extract_inline_source_map(source))
+      .flatten();
+    let (source, source_map) = match inline_source_map {
+      Some((pos, source_map)) if strip_inline_source_map => {
+        (&source[..pos], Some(source_map))
+      }
+      Some((_, source_map)) => (source, Some(source_map)),
+      None if is_code => (
+        source,
+        self
+          .load_referenced_source_map(url, media_type, source, file_fetcher)
+          .await,
+      ),
+      None => (source, None),
+    };
+    create_module_response_js(url, media_type, source, source_map.as_deref())
+  }
+
+  /// Loads the source map referenced by a `sourceMappingURL` comment in
+  /// code that wasn't transpiled (ex. JavaScript in npm packages).
+  async fn load_referenced_source_map(
+    &self,
+    url: &Url,
+    media_type: MediaType,
+    source: &[u8],
+    file_fetcher: &WasmFileFetcher,
+  ) -> Option<Vec<u8>> {
+    let comment_style = match media_type {
+      MediaType::JavaScript
+      | MediaType::Jsx
+      | MediaType::Mjs
+      | MediaType::Cjs => SourceMapCommentStyle::Line,
+      MediaType::Css => SourceMapCommentStyle::Block,
+      _ => return None,
+    };
+    let reference = find_source_mapping_url(source, comment_style)?;
+    let source_map_url = match url.join(reference) {
+      Ok(source_map_url) => source_map_url,
+      Err(err) => {
+        log::debug!(
+          "Failed resolving source map '{}' for {}: {:#}",
+          reference,
+          url,
+          err
+        );
+        return None;
+      }
+    };
+    match self.fetch_file(file_fetcher, &source_map_url).await {
+      Ok(file) => Some(file.source.to_vec()),
+      Err(err) => {
+        log::debug!(
+          "Failed loading source map {} for {}: {:#}",
+          source_map_url,
+          url,
+          err
+        );
+        None
+      }
+    }
+  }
+
+  /// Reports the installed npm packages whose tarball wasn't
+  /// downloaded by the install.
+  fn report_npm_package_cache_hits(&self, packages: &HashSet<PackageNv>) {
+    let snapshot = self.resolver_factory.npm_resolution().snapshot();
+    let mut pending = packages.clone();
+    for package in snapshot.all_packages_for_every_system() {
+      if let Some(dist) = &package.dist
+        && pending.remove(&package.id.nv)
+      {
+        self
+          .progress_reporter
+          .report_npm_package_if_cache_hit(&package.id.nv, &dist.tarball);
+      }
+    }
+  }
+
+  /// Fetches a file, checking the permissions of the specifier and
+  /// of any redirects.
+  async fn fetch_file(
+    &self,
+    file_fetcher: &WasmFileFetcher,
+    specifier: &Url,
+  ) -> Result<File, anyhow::Error> {
+    let file = file_fetcher
+      .fetch_with_options(
+        specifier,
+        FetchPermissionsOptionRef::Restricted(
+          self.permissions.as_ref(),
+          CheckSpecifierKind::Static,
+        ),
+        FetchOptions::default(),
+      )
+      .await?;
+    // modules in the graph were already reported when loaded
+    if !self.graph.get().contains(&file.url) {
+      self.progress_reporter.report_if_cache_hit(&file.url);
+    }
+    Ok(file)
+  }
+
+  /// Gets a file fetcher whose downloads are aborted with the signal.
+  fn file_fetcher_for_signal(
+    &self,
+    signal: &JsValue,
+  ) -> Result<Arc<WasmFileFetcher>, anyhow::Error> {
+    match SyncAbortSignal::from_js_value(signal) {
+      Some(signal) => create_file_fetcher(
+        &self.workspace_factory,
+        self.http_client.with_abort_signal(signal),
+        self.memory_files.clone(),
+        self.cache_setting.clone(),
+      ),
+      None => Ok(self.file_fetcher.clone()),
+    }
+  }
+
   async fn maybe_transpile(
     &self,
     specifier: &Url,
@@ -842,6 +1565,7 @@ impl DenoLoader {
     &self,
     specifier: Cow<str>,
     resolution_mode: node_resolver::ResolutionMode,
+    resolution_kind: node_resolver::NodeResolutionKind,
   ) -> Result<Url, anyhow::Error> {
     let cwd = self.workspace_factory.initial_cwd();
     if specifier.contains('\\') {
@@ -856,7 +1580,7 @@ impl DenoLoader {
       &referrer,
       Position::zeroed(),
       resolution_mode,
-      node_resolver::NodeResolutionKind::Execution,
+      resolution_kind,
     )?)
   }
 
@@ -981,28 +1705,73 @@ fn deno_resolve_error_code(err: &DenoResolveError) -> Option<NodeJsErrorCode> {
 const SOURCE_MAP_PREFIX: &str =
   "//# sourceMappingURL=data:application/json;base64,";
 
+/// How the source map of transpiled code is provided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SourceMapMode {
+  /// Keep the inline source map comment in the code.
+  Inline,
+  /// Remove the inline source map comment from the code and only
+  /// provide it via the `sourceMap` property.
+  External,
+}
+
 /// Extracts an inline source map from transpiled source code.
-/// Returns the decoded source map JSON bytes, if found.
-fn extract_inline_source_map(source: &[u8]) -> Option<Vec<u8>> {
+/// Returns the byte index of the comment and the decoded source
+/// map JSON bytes, if found.
+fn extract_inline_source_map(source: &[u8]) -> Option<(usize, Vec<u8>)> {
   let source_str = std::str::from_utf8(source).ok()?;
   let pos = source_str.rfind(SOURCE_MAP_PREFIX)?;
   let base64_start = pos + SOURCE_MAP_PREFIX.len();
   let base64_data = source_str[base64_start..].trim_end();
-  base64::engine::general_purpose::STANDARD
+  let source_map = base64::engine::general_purpose::STANDARD
     .decode(base64_data)
-    .ok()
+    .ok()?;
+  Some((pos, source_map))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SourceMapCommentStyle {
+  /// `//# sourceMappingURL=...`
+  Line,
+  /// `/*# sourceMappingURL=... */`
+  Block,
+}
+
+/// Finds the reference in the last `sourceMappingURL` comment.
+fn find_source_mapping_url(
+  source: &[u8],
+  comment_style: SourceMapCommentStyle,
+) -> Option<&str> {
+  const PREFIX: &str = "sourceMappingURL=";
+  let source = std::str::from_utf8(source).ok()?;
+  let line = source
+    .lines()
+    .rev()
+    .map(|line| line.trim())
+    .find(|line| !line.is_empty())?;
+  let comment = match comment_style {
+    SourceMapCommentStyle::Line => line
+      .strip_prefix("//#")
+      .or_else(|| line.strip_prefix("//@"))?,
+    SourceMapCommentStyle::Block => line
+      .strip_prefix("/*#")
+      .or_else(|| line.strip_prefix("/*@"))?
+      .strip_suffix("*/")?,
+  };
+  let reference = comment.trim_start().strip_prefix(PREFIX)?.trim();
+  if reference.is_empty() {
+    None
+  } else {
+    Some(reference)
+  }
 }
 
-fn create_module_response(
+fn create_module_response_js(
   url: &Url,
   media_type: MediaType,
   source: &[u8],
+  source_map: Option<&[u8]>,
 ) -> JsValue {
-  let source_map = if media_type.is_emittable() {
-    extract_inline_source_map(source)
-  } else {
-    None
-  };
   let obj = Object::new();
   js_sys::Reflect::set(
     &obj,
@@ -1022,7 +1791,7 @@ fn create_module_response(
   let code = Uint8Array::from(source);
   js_sys::Reflect::set(&obj, &JsValue::from_str("code"), &code).unwrap();
   if let Some(sm) = source_map {
-    let sm_array = Uint8Array::from(sm.as_slice());
+    let sm_array = Uint8Array::from(sm);
     js_sys::Reflect::set(&obj, &JsValue::from_str("sourceMap"), &sm_array)
       .unwrap();
   }
@@ -1056,10 +1825,48 @@ fn resolve_absolute_path(
   }
 }
 
+type WasmFileFetcher =
+  PermissionedFileFetcher<NullBlobStore, RealSys, WasmHttpClient>;
+
+fn create_file_fetcher(
+  workspace_factory: &WorkspaceFactory<RealSys>,
+  http_client: WasmHttpClient,
+  memory_files: MemoryFilesRc,
+  cache_setting: CacheSetting,
+) -> Result<Arc<WasmFileFetcher>, anyhow::Error> {
+  Ok(Arc::new(PermissionedFileFetcher::new(
+    NullBlobStore,
+    Arc::new(workspace_factory.http_cache()?.clone()),
+    http_client,
+    memory_files,
+    workspace_factory.sys().clone(),
+    PermissionedFileFetcherOptions {
+      allow_remote: true,
+      cache_setting,
+    },
+  )))
+}
+
 fn create_js_error(err: &anyhow::Error) -> JsValue {
   wasm_bindgen::JsError::new(&format!("{:#}", err)).into()
 }
 
+fn create_load_js_error(err: &anyhow::Error) -> JsValue {
+  let err_value = create_js_error(err);
+  let is_access_denied = err.downcast_ref::<PermissionDeniedError>().is_some()
+    || err
+      .downcast_ref::<FetchError>()
+      .is_some_and(|err| err.get_class() == ACCESS_DENIED_CLASS);
+  if is_access_denied {
+    _ = js_sys::Reflect::set(
+      &err_value,
+      &JsValue::from_str("code"),
+      &JsValue::from_str(ACCESS_DENIED_CODE),
+    );
+  }
+  err_value
+}
+
 fn parse_resolution_mode(resolution_mode: u8) -> node_resolver::ResolutionMode {
   match resolution_mode {
     1 => node_resolver::ResolutionMode::Require,
@@ -1067,6 +1874,96 @@ fn parse_resolution_mode(resolution_mode: u8) -> node_resolver::ResolutionMode {
   }
 }
 
+fn u8_to_media_type(value: u8) -> Result<MediaType, anyhow::Error> {
+  Ok(match value {
+    0 => MediaType::JavaScript,
+    1 => MediaType::Jsx,
+    2 => MediaType::Mjs,
+    3 => MediaType::Cjs,
+    4 => MediaType::TypeScript,
+    5 => MediaType::Mts,
+    6 => MediaType::Cts,
+    7 => MediaType::Dts,
+    8 => MediaType::Dmts,
+    9 => MediaType::Dcts,
+    10 => MediaType::Tsx,
+    11 => MediaType::Css,
+    12 => MediaType::Json,
+    13 => MediaType::Jsonc,
+    14 => MediaType::Json5,
+    15 => MediaType::Html,
+    16 => MediaType::Markdown,
+    17 => MediaType::Sql,
+    18 => MediaType::Wasm,
+    19 => MediaType::SourceMap,
+    20 => MediaType::Unknown,
+    _ => bail!("Invalid media type: {}", value),
+  })
+}
+
+/// Content type to store with in-memory files so that the
+/// media type is not inferred from the specifier's extension.
+fn media_type_to_content_type(media_type: MediaType) -> Option<&'static str> {
+  match media_type {
+    MediaType::JavaScript | MediaType::Mjs | MediaType::Cjs => {
+      Some("application/javascript")
+    }
+    MediaType::Jsx => Some("text/jsx"),
+    MediaType::TypeScript
+    | MediaType::Mts
+    | MediaType::Cts
+    | MediaType::Dts
+    | MediaType::Dmts
+    | MediaType::Dcts => Some("application/typescript"),
+    MediaType::Tsx => Some("text/tsx"),
+    MediaType::Css => Some("text/css"),
+    MediaType::Json | MediaType::Jsonc | MediaType::Json5 => {
+      Some("application/json")
+    }
+    MediaType::Html => Some("text/html"),
+    MediaType::Markdown => Some("text/markdown"),
+    MediaType::Wasm => Some("application/wasm"),
+    MediaType::Sql | MediaType::SourceMap | MediaType::Unknown => None,
+  }
+}
+
+fn parse_resolution_kind(
+  resolution_kind: u8,
+) -> node_resolver::NodeResolutionKind {
+  match resolution_kind {
+    1 => node_resolver::NodeResolutionKind::Types,
+    _ => node_resolver::NodeResolutionKind::Execution,
+  }
+}
+
+/// Resolves the types of a dependency that has a `@ts-types` directive.
+fn resolve_ts_types_directive(
+  graph: &ModuleGraph,
+  specifier: &str,
+  referrer: &Url,
+) -> Option<Url> {
+  let Some(Module::Js(module)) = graph.get(referrer) else {
+    return None;
+  };
+  let dependency = module.dependencies.get(specifier)?;
+  let types_specifier = dependency.maybe_type.maybe_specifier()?;
+  Some(graph.resolve(types_specifier).clone())
+}
+
+/// Follows a module's `X-TypeScript-Types` header or `@ts-self-types`
+/// directive to its declaration file.
+fn resolve_types_dependency(graph: &ModuleGraph, specifier: Url) -> Url {
+  match graph.get(&specifier) {
+    Some(Module::Js(module)) => module
+      .maybe_types_dependency
+      .as_ref()
+      .and_then(|d| d.dependency.maybe_specifier())
+      .map(|s| graph.resolve(s).clone())
+      .unwrap_or(specifier),
+    _ => specifier,
+  }
+}
+
 fn media_type_to_u8(media_type: MediaType) -> u8 {
   match media_type {
     MediaType::JavaScript => 0,
@@ -1114,6 +2011,184 @@ fn npm_system_info() -> Result<NpmSystemInfo, anyhow::Error> {
   })
 }
 
+/// Gets the provided modules along with all the modules that
+/// depend on them directly or transitively.
+fn invalidated_modules(
+  graph: &ModuleGraph,
+  specifiers: &[Url],
+) -> HashSet<Url> {
+  let mut dependents: HashMap<&Url, Vec<&Url>> = HashMap::new();
+  for module in graph.modules() {
+    let dependencies = match module {
+      Module::Js(module) => &module.dependencies,
+      Module::Wasm(module) => &module.dependencies,
+      Module::Json(_)
+      | Module::Npm(_)
+      | Module::Node(_)
+      | Module::External(_) => continue,
+    };
+    let mut add_dependent = |specifier: &Url| {
+      dependents
+        .entry(graph.resolve(specifier))
+        .or_default()
+        .push(module.specifier());
+    };
+    for dependency in dependencies.values() {
+      if let Some(specifier) = dependency.maybe_code.maybe_specifier() {
+        add_dependent(specifier);
+      }
+      if let Some(specifier) = dependency.maybe_type.maybe_specifier() {
+        add_dependent(specifier);
+      }
+    }
+    if let Module::Js(module) = module
+      && let Some(types_dep) = &module.maybe_types_dependency
+      && let Some(specifier) = types_dep.dependency.maybe_specifier()
+    {
+      add_dependent(specifier);
+    }
+  }
+
+  let mut invalidated = specifiers
+    .iter()
+    .map(|s| graph.resolve(s).clone())
+    .collect::<HashSet<_>>();
+  let mut pending = invalidated.iter().cloned().collect::<Vec<_>>();
+  while let Some(specifier) = pending.pop() {
+    if let Some(dependents) = dependents.get(&specifier) {
+      for dependent in dependents {
+        if invalidated.insert((*dependent).clone()) {
+          pending.push((*dependent).clone());
+        }
+      }
+    }
+  }
+  invalidated
+}
+
+/// Import map provided via the workspace options, which is applied on
+/// top of the `imports` and `scopes` of the workspace config.
+#[derive(Debug)]
+struct ImportMapOverrideProvider {
+  sys: RealSys,
+  cwd: PathBuf,
+  workspace_factory: Arc<WorkspaceFactory<RealSys>>,
+  /// Path or file: URL to the import map or the inline import map.
+  value: serde_json::Value,
+}
+
+#[async_trait::async_trait(?Send)]
+impl SpecifiedImportMapProvider for ImportMapOverrideProvider {
+  async fn get(&self) -> Result<Option<SpecifiedImportMap>, anyhow::Error> {
+    let import_map = self.read_import_map()?;
+    let workspace_dir = self.workspace_factory.workspace_directory()?;
+    let Some(deno_json) = workspace_dir.workspace.root_deno_json() else {
+      return Ok(Some(import_map));
+    };
+    let config_import_map = serde_json::json!({
+      "imports": deno_json.json.imports,
+      "scopes": deno_json.json.scopes,
+    });
+    Ok(Some(SpecifiedImportMap {
+      value: merge_import_maps(&[
+        (&config_import_map, &deno_json.specifier),
+        (&import_map.value, &import_map.base_url),
+      ]),
+      base_url: import_map.base_url,
+    }))
+  }
+}
+
+impl ImportMapOverrideProvider {
+  fn read_import_map(&self) -> Result<SpecifiedImportMap, anyhow::Error> {
+    let import_map = match &self.value {
+      serde_json::Value::String(path) => {
+        let path = resolve_absolute_path(path.clone(), &self.cwd)?;
+        let text = self.sys.fs_read_to_string(&path).with_context(|| {
+          format!("Failed reading import map '{}'.", path.display())
+        })?;
+        SpecifiedImportMap {
+          base_url: deno_path_util::url_from_file_path(&path)?,
+          value: serde_json::from_str(&text).with_context(|| {
+            format!("Failed parsing import map '{}'.", path.display())
+          })?,
+        }
+      }
+      value @ serde_json::Value::Object(_) => SpecifiedImportMap {
+        base_url: deno_path_util::url_from_directory_path(&self.cwd)?,
+        value: value.clone(),
+      },
+      _ => bail!("The import map must be a path, file: URL or object."),
+    };
+    Ok(import_map)
+  }
+}
+
+/// Merges the import maps, with the entries of later import maps taking
+/// precedence. Relative specifiers are resolved from the base url of
+/// their import map so the merged import map has no relative entries.
+fn merge_import_maps(
+  import_maps: &[(&serde_json::Value, &Url)],
+) -> serde_json::Value {
+  fn add_specifier_map(
+    target: &mut serde_json::Map<String, serde_json::Value>,
+    specifier_map: &serde_json::Value,
+    base_url: &Url,
+  ) {
+    let Some(specifier_map) = specifier_map.as_object() else {
+      return;
+    };
+    for (key, value) in specifier_map {
+      let value = match value {
+        serde_json::Value::String(address) => {
+          serde_json::Value::String(resolve_relative(address, base_url))
+        }
+        value => value.clone(),
+      };
+      target.insert(resolve_relative(key, base_url), value);
+    }
+  }
+
+  fn resolve_relative(specifier: &str, base_url: &Url) -> String {
+    if specifier.starts_with("./")
+      || specifier.starts_with("../")
+      || specifier.starts_with('/')
+    {
+      match base_url.join(specifier) {
+        Ok(url) => url.into(),
+        Err(_) => specifier.to_string(),
+      }
+    } else {
+      specifier.to_string()
+    }
+  }
+
+  let mut imports = serde_json::Map::new();
+  let mut scopes = serde_json::Map::new();
+  for (import_map, base_url) in import_maps {
+    if let Some(specifier_map) = import_map.get("imports") {
+      add_specifier_map(&mut imports, specifier_map, base_url);
+    }
+    let Some(import_map_scopes) =
+      import_map.get("scopes").and_then(|s| s.as_object())
+    else {
+      continue;
+    };
+    for (scope, specifier_map) in import_map_scopes {
+      let target = scopes
+        .entry(resolve_relative(scope, base_url))
+        .or_insert_with(|| serde_json::Value::Object(Default::default()));
+      if let Some(target) = target.as_object_mut() {
+        add_specifier_map(target, specifier_map, base_url);
+      }
+    }
+  }
+  serde_json::json!({
+    "imports": imports,
+    "scopes": scopes,
+  })
+}
+
 struct ModuleGraphCell {
   graph: RefCell<Rc<ModuleGraph>>,
 }
@@ -0,0 +1,193 @@
+use std::borrow::Cow;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use deno_error::JsErrorBox;
+use deno_graph::ModuleSpecifier;
+use deno_graph::source::LoadError;
+use deno_graph::source::LoadFuture;
+use deno_graph::source::LoadOptions;
+use deno_graph::source::Loader;
+use deno_resolver::file_fetcher::CheckSpecifierKind;
+use deno_resolver::file_fetcher::FetchPermissions;
+use serde::Deserialize;
+
+use crate::resolve_absolute_path;
+
+/// Error code for loads that the permissions don't allow.
+pub const ACCESS_DENIED_CODE: &str = "ERR_ACCESS_DENIED";
+/// Class of the error surfaced through deno_graph for denied loads.
+pub const ACCESS_DENIED_CLASS: &str = "NotCapable";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionsOptions {
+  #[serde(default)]
+  pub read: Option<AllowListOption>,
+  #[serde(default)]
+  pub net: Option<AllowListOption>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum AllowListOption {
+  All(bool),
+  List(Vec<String>),
+}
+
+#[derive(Debug)]
+pub struct PermissionDeniedError {
+  message: String,
+}
+
+impl std::fmt::Display for PermissionDeniedError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.message)
+  }
+}
+
+impl std::error::Error for PermissionDeniedError {}
+
+/// The local paths and network hosts the loader may read from.
+///
+/// `None` means everything is allowed.
+#[derive(Debug, Default)]
+pub struct Permissions {
+  read: Option<Vec<PathBuf>>,
+  net: Option<Vec<String>>,
+  /// The npm cache and node_modules folders, which packages are read
+  /// from after being resolved by the loader.
+  npm_dirs: Vec<PathBuf>,
+}
+
+impl Permissions {
+  pub fn from_options(
+    options: PermissionsOptions,
+    cwd: &Path,
+    npm_dirs: Vec<PathBuf>,
+  ) -> Result<Self, anyhow::Error> {
+    let read = match options.read {
+      None | Some(AllowListOption::All(true)) => None,
+      Some(AllowListOption::All(false)) => Some(Vec::new()),
+      Some(AllowListOption::List(paths)) => Some(
+        paths
+          .into_iter()
+          .map(|path| {
+            let path = resolve_absolute_path(path, cwd)?;
+            // otherwise "../" would escape the allowed folder
+            Ok::<_, anyhow::Error>(
+              deno_path_util::normalize_path(Cow::Owned(path)).into_owned(),
+            )
+          })
+          .collect::<Result<Vec<_>, _>>()?,
+      ),
+    };
+    let net = match options.net {
+      None | Some(AllowListOption::All(true)) => None,
+      Some(AllowListOption::All(false)) => Some(Vec::new()),
+      Some(AllowListOption::List(hosts)) => Some(
+        hosts
+          .into_iter()
+          .map(|host| host.to_ascii_lowercase())
+          .collect(),
+      ),
+    };
+    Ok(Self {
+      read,
+      net,
+      npm_dirs,
+    })
+  }
+
+  pub fn check_specifier(
+    &self,
+    specifier: &ModuleSpecifier,
+  ) -> Result<(), PermissionDeniedError> {
+    match specifier.scheme() {
+      "file" => {
+        let Some(allowed_paths) = &self.read else {
+          return Ok(());
+        };
+        let Ok(path) = deno_path_util::url_to_file_path(specifier) else {
+          return Err(Self::denied("read", specifier.as_str()));
+        };
+        let path = deno_path_util::normalize_path(Cow::Owned(path));
+        if allowed_paths
+          .iter()
+          .chain(self.npm_dirs.iter())
+          .any(|allowed| path.starts_with(allowed))
+        {
+          Ok(())
+        } else {
+          Err(Self::denied("read", &path.display().to_string()))
+        }
+      }
+      "http" | "https" => {
+        let Some(allowed_hosts) = &self.net else {
+          return Ok(());
+        };
+        let Some(host) = specifier.host_str() else {
+          return Err(Self::denied("net", specifier.as_str()));
+        };
+        let host = host.to_ascii_lowercase();
+        let host_with_port = specifier
+          .port_or_known_default()
+          .map(|port| format!("{}:{}", host, port));
+        if allowed_hosts.iter().any(|allowed| {
+          *allowed == host || Some(allowed) == host_with_port.as_ref()
+        }) {
+          Ok(())
+        } else {
+          Err(Self::denied(
+            "net",
+            host_with_port.as_deref().unwrap_or(&host),
+          ))
+        }
+      }
+      _ => Ok(()),
+    }
+  }
+
+  fn denied(kind: &str, value: &str) -> PermissionDeniedError {
+    PermissionDeniedError {
+      message: format!(
+        "Requires {} access to \"{}\", which is not allowed by the loader permissions.",
+        kind, value
+      ),
+    }
+  }
+}
+
+impl FetchPermissions for Permissions {
+  fn check_specifier(
+    &self,
+    specifier: &ModuleSpecifier,
+    _kind: CheckSpecifierKind,
+  ) -> Result<(), JsErrorBox> {
+    Permissions::check_specifier(self, specifier)
+      .map_err(|err| JsErrorBox::new(ACCESS_DENIED_CLASS, err.to_string()))
+  }
+}
+
+/// Checks the permissions before loading modules into the graph.
+pub struct PermissionedGraphLoader<'a, TLoader: Loader> {
+  pub inner: &'a TLoader,
+  pub permissions: &'a Permissions,
+}
+
+impl<TLoader: Loader> Loader for PermissionedGraphLoader<'_, TLoader> {
+  fn load(
+    &self,
+    specifier: &ModuleSpecifier,
+    options: LoadOptions,
+  ) -> LoadFuture {
+    if let Err(err) = self.permissions.check_specifier(specifier) {
+      let err = JsErrorBox::new(ACCESS_DENIED_CLASS, err.to_string());
+      return Box::pin(std::future::ready(Err(LoadError::Other(Arc::new(
+        err,
+      )))));
+    }
+    self.inner.load(specifier, options)
+  }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use deno_graph::ModuleSpecifier;
+use deno_semver::package::PackageNv;
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::js_function::SyncJsFunction;
+
+#[wasm_bindgen]
+extern "C" {
+  #[wasm_bindgen(js_namespace = console)]
+  fn error(s: &JsValue);
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ProgressEvent<'a> {
+  #[serde(rename_all = "camelCase")]
+  DownloadStart { url: &'a str },
+  #[serde(rename_all = "camelCase")]
+  DownloadEnd {
+    url: &'a str,
+    status: u16,
+    bytes: usize,
+  },
+  #[serde(rename_all = "camelCase")]
+  DownloadFailed { url: &'a str, message: &'a str },
+  /// The remote module or npm package (`npm:name@version`) was served
+  /// from the cache or the server responded that the cached version is
+  /// still valid.
+  #[serde(rename_all = "camelCase")]
+  CacheHit { url: &'a str },
+  /// An npm package was added to the npm resolution.
+  #[serde(rename_all = "camelCase")]
+  NpmPackageResolved { package: &'a str },
+  #[serde(rename_all = "camelCase")]
+  ModuleLoaded {
+    specifier: &'a str,
+    modules_done: usize,
+    modules_total: usize,
+  },
+  /// Waiting on a lock held by another process (ex. the npm cache).
+  #[serde(rename_all = "camelCase")]
+  Blocking { message: &'a str },
+  #[serde(rename_all = "camelCase")]
+  InstallingPackage { message: &'a str },
+}
+
+/// Reports progress to the `onProgress` callback or, when none was
+/// provided, logs the notable events to the console unless quiet.
+#[derive(Debug, Default, Clone)]
+pub struct ProgressReporter {
+  pub on_progress: Option<SyncJsFunction>,
+  pub quiet: bool,
+  /// Urls of modules and npm tarballs that were downloaded, used to
+  /// tell when something was served from the cache instead. Entries
+  /// are removed once checked.
+  pub downloaded_urls: Arc<Mutex<HashSet<String>>>,
+}
+
+impl ProgressReporter {
+  pub fn report(&self, event: ProgressEvent) {
+    if let Some(on_progress) = &self.on_progress {
+      match serde_wasm_bindgen::to_value(&event) {
+        Ok(value) => {
+          if let Err(err) = on_progress.call1(&value) {
+            log::warn!("Failed calling onProgress: {:?}", err);
+          }
+        }
+        Err(err) => {
+          log::warn!("Failed serializing progress event: {:#}", err);
+        }
+      }
+      return;
+    }
+    if self.quiet {
+      return;
+    }
+    let message = match event {
+      ProgressEvent::DownloadStart { url } => format!("Downloading {}", url),
+      ProgressEvent::Blocking { message } => format!("Blocking {}", message),
+      ProgressEvent::InstallingPackage { message } => {
+        format!("Initialize {}", message)
+      }
+      ProgressEvent::DownloadEnd { .. }
+      | ProgressEvent::DownloadFailed { .. }
+      | ProgressEvent::CacheHit { .. }
+      | ProgressEvent::NpmPackageResolved { .. }
+      | ProgressEvent::ModuleLoaded { .. } => return,
+    };
+    error(&JsValue::from(message));
+  }
+
+  /// Records a download so it's not reported as a cache hit.
+  pub fn record_download(&self, url: &str) {
+    self.downloaded_urls.lock().unwrap().insert(url.to_string());
+  }
+
+  fn take_download(&self, url: &str) -> bool {
+    self.downloaded_urls.lock().unwrap().remove(url)
+  }
+
+  /// Reports a remote module that was loaded without downloading it.
+  pub fn report_if_cache_hit(&self, specifier: &ModuleSpecifier) {
+    if matches!(specifier.scheme(), "http" | "https")
+      && !self.take_download(specifier.as_str())
+    {
+      self.report(ProgressEvent::CacheHit {
+        url: specifier.as_str(),
+      });
+    }
+  }
+
+  /// Reports an installed npm package whose tarball was already
+  /// in the npm cache.
+  pub fn report_npm_package_if_cache_hit(
+    &self,
+    nv: &PackageNv,
+    tarball_url: &str,
+  ) {
+    if !self.take_download(tarball_url) {
+      self.report(ProgressEvent::CacheHit {
+        url: &format!("npm:{}", nv),
+      });
+    }
+  }
+}
+
+impl deno_npm_installer::Reporter for ProgressReporter {
+  type Guard = ();
+  type ClearGuard = ();
+
+  fn on_blocking(&self, message: &str) -> Self::Guard {
+    self.report(ProgressEvent::Blocking { message });
+  }
+
+  fn on_initializing(&self, message: &str) -> Self::Guard {
+    self.report(ProgressEvent::InstallingPackage { message });
+  }
+
+  fn clear_guard(&self) -> Self::ClearGuard {}
+}
+
+impl deno_graph::source::Reporter for ProgressReporter {
+  fn on_load(
+    &self,
+    specifier: &ModuleSpecifier,
+    modules_done: usize,
+    modules_total: usize,
+  ) {
+    self.report_if_cache_hit(specifier);
+    self.report(ProgressEvent::ModuleLoaded {
+      specifier: specifier.as_str(),
+      modules_done,
+      modules_total,
+    });
+  }
+}
@@ -0,0 +1,93 @@
+import { assertEquals, assertRejects } from "@std/assert";
+import {
+  createLoaderWithDiagnostics,
+  RequestedModuleType,
+  Workspace,
+} from "../helpers.ts";
+
+Deno.test("aborts adding entrypoints", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const workspace = new Workspace({
+    cacheDir,
+    noConfig: true,
+    // never responds
+    fetch: () => new Promise(() => {}),
+  });
+  const loader = await workspace.createLoader();
+  const controller = new AbortController();
+  const promise = loader.addEntrypoints(["https://example.com/mod.js"], {
+    signal: controller.signal,
+  });
+  controller.abort(new Error("Cancelled."));
+  await assertRejects(() => promise, Error, "Cancelled.");
+});
+
+Deno.test("aborts loading", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const workspace = new Workspace({
+    cacheDir,
+    noConfig: true,
+    fetch: () => new Promise(() => {}),
+  });
+  const loader = await workspace.createLoader();
+  await assertRejects(
+    () =>
+      loader.load("https://example.com/mod.js", RequestedModuleType.Default, {
+        signal: AbortSignal.abort(new Error("Cancelled.")),
+      }),
+    Error,
+    "Cancelled.",
+  );
+});
+
+Deno.test("times out requests", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  let attempts = 0;
+  const { diagnostics } = await createLoaderWithDiagnostics({
+    cacheDir,
+    noConfig: true,
+    requestTimeout: 10,
+    retry: { maxRetries: 1, initialDelayMs: 1 },
+    fetch: (_url, init) => {
+      attempts++;
+      const signal = init.signal!;
+      return new Promise((_resolve, reject) => {
+        signal.addEventListener("abort", () => reject(signal.reason));
+      });
+    },
+  }, {
+    entrypoints: ["https://example.com/mod.js"],
+  });
+
+  assertEquals(attempts, 2);
+  assertEquals(diagnostics.length, 1);
+});
+
+Deno.test("aborts in-flight requests", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const { promise: requestSignal, resolve } = Promise.withResolvers<
+    AbortSignal
+  >();
+  const workspace = new Workspace({
+    cacheDir,
+    noConfig: true,
+    fetch: (_url, init) => {
+      const signal = init.signal!;
+      resolve(signal);
+      return new Promise((_resolve, reject) => {
+        signal.addEventListener("abort", () => reject(signal.reason));
+      });
+    },
+  });
+  const loader = await workspace.createLoader();
+  const controller = new AbortController();
+  const promise = loader.load(
+    "https://example.com/mod.js",
+    RequestedModuleType.Default,
+    { signal: controller.signal },
+  );
+  const signal = await requestSignal;
+  controller.abort(new Error("Cancelled."));
+  await assertRejects(() => promise, Error, "Cancelled.");
+  assertEquals(signal.aborted, true);
+});
@@ -0,0 +1,43 @@
+import { assertEquals, assertThrows } from "@std/assert";
+import { createLoader, Workspace } from "../helpers.ts";
+
+Deno.test("sends auth tokens to matching hosts", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const authHeaders: Record<string, string | undefined> = {};
+  const entrypoint = "https://raw.example.com/mod.js";
+  await createLoader({
+    cacheDir,
+    noConfig: true,
+    authTokens: "secret@example.com;user:pw@other.com:8080",
+    fetch: (url, init) => {
+      authHeaders[url] = init.headers["authorization"];
+      const code = url === entrypoint
+        ? [
+          `import "https://other.com:8080/a.js";`,
+          `import "https://other.com/b.js";`,
+        ].join("\n")
+        : "";
+      return Promise.resolve(
+        new Response(code, {
+          headers: { "content-type": "application/javascript" },
+        }),
+      );
+    },
+  }, {
+    entrypoints: [entrypoint],
+  });
+
+  assertEquals(authHeaders, {
+    [entrypoint]: "Bearer secret",
+    "https://other.com:8080/a.js": `Basic ${btoa("user:pw")}`,
+    "https://other.com/b.js": undefined,
+  });
+});
+
+Deno.test("errors for invalid auth tokens", () => {
+  assertThrows(
+    () => new Workspace({ noConfig: true, authTokens: "secret" }),
+    Error,
+    "Invalid auth token entry '***'.",
+  );
+});
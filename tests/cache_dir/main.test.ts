@@ -0,0 +1,20 @@
+import { assert } from "@std/assert";
+import { createLoader } from "../helpers.ts";
+
+Deno.test("caches remote modules in the provided cache dir", async () => {
+  await using server = Deno.serve((_request) => {
+    return new Response("console.log(1);", {
+      headers: {
+        "content-type": "application/javascript",
+      },
+    });
+  });
+  const cacheDir = await Deno.makeTempDir();
+  const url = `http://localhost:${server.addr.port}/mod.js`;
+  await createLoader({ cacheDir, noConfig: true }, {
+    entrypoints: [url],
+  });
+
+  const stat = await Deno.stat(cacheDir + "/remote");
+  assert(stat.isDirectory);
+});
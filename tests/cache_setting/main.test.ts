@@ -0,0 +1,56 @@
+import { assertEquals } from "@std/assert";
+import {
+  createLoader,
+  createLoaderWithDiagnostics,
+  type WorkspaceOptions,
+} from "../helpers.ts";
+
+Deno.test("respects the cache setting", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const url = "https://example.com/mod.js";
+  let downloads = 0;
+  const options: WorkspaceOptions = {
+    cacheDir,
+    noConfig: true,
+    fetch: () => {
+      downloads++;
+      return Promise.resolve(
+        new Response("console.log(1);", {
+          headers: { "content-type": "application/javascript" },
+        }),
+      );
+    },
+  };
+  const loaderOptions = { entrypoints: [url] };
+
+  // errors when not cached
+  {
+    const { diagnostics } = await createLoaderWithDiagnostics({
+      ...options,
+      cacheSetting: "only",
+    }, loaderOptions);
+    assertEquals(diagnostics.length, 1);
+    assertEquals(downloads, 0);
+  }
+
+  await createLoader({ ...options, cacheSetting: "use" }, loaderOptions);
+  assertEquals(downloads, 1);
+
+  await createLoader({ ...options, cacheSetting: "only" }, loaderOptions);
+  assertEquals(downloads, 1);
+
+  await createLoader({ ...options, cacheSetting: "reload" }, loaderOptions);
+  assertEquals(downloads, 2);
+
+  await createLoader({
+    ...options,
+    cacheSetting: ["https://other.com/"],
+  }, loaderOptions);
+  assertEquals(downloads, 2);
+
+  await createLoader({
+    ...options,
+    cacheSetting: ["https://example.com/"],
+  }, loaderOptions);
+  assertEquals(downloads, 3);
+});
@@ -0,0 +1,62 @@
+import { assert, assertEquals } from "@std/assert";
+import {
+  assertResponseText,
+  createLoader,
+  MediaType,
+  type ModuleLoadResponse,
+  RequestedModuleType,
+  type WorkspaceOptions,
+} from "../helpers.ts";
+
+const scriptFile = import.meta.dirname + "/testdata/script.js";
+const scriptSource = "module.exports.value = 1;\n";
+
+async function loadScript(
+  cjsDetection: WorkspaceOptions["cjsDetection"],
+  translateCjs = true,
+) {
+  const { loader } = await createLoader({
+    configPath: import.meta.dirname + "/testdata/deno.json",
+    cjsDetection,
+    translateCjs,
+  }, {
+    entrypoints: [scriptFile],
+  });
+  return await loader.load(
+    import.meta.resolve("./testdata/script.js"),
+    RequestedModuleType.Default,
+  );
+}
+
+Deno.test("explicit only treats a commonjs type as CommonJS", async () => {
+  assertResponseText(await loadScript("explicit"), scriptSource);
+});
+
+Deno.test("implicit treats scripts without a type as CommonJS", async () => {
+  const response = await loadScript("implicit") as ModuleLoadResponse;
+  const code = new TextDecoder().decode(response.code);
+  assert(code.includes("export default"), code);
+  assert(!code.startsWith("module.exports"), code);
+});
+
+Deno.test("disabled never treats scripts as CommonJS", async () => {
+  assertResponseText(await loadScript("disabled"), scriptSource);
+});
+
+Deno.test("detects CommonJS without translating it", async () => {
+  for (
+    const [cjsDetection, mediaType] of [
+      ["explicit", MediaType.JavaScript],
+      ["implicit", MediaType.Cjs],
+      ["disabled", MediaType.JavaScript],
+    ] as const
+  ) {
+    const response = await loadScript(cjsDetection, false);
+    assertEquals(
+      (response as ModuleLoadResponse).mediaType,
+      mediaType,
+      cjsDetection,
+    );
+    assertResponseText(response, scriptSource);
+  }
+});
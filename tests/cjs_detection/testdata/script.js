@@ -0,0 +1 @@
+module.exports.value = 1;
@@ -0,0 +1,75 @@
+import { assert, assertEquals, assertMatch } from "@std/assert";
+import {
+  assertResponseText,
+  createLoader,
+  type ModuleLoadResponse,
+  RequestedModuleType,
+  ResolutionMode,
+} from "../helpers.ts";
+
+const configPath = import.meta.dirname + "/testdata/deno.json";
+const mainFile = import.meta.dirname + "/testdata/main.ts";
+const cjsSource = `module.exports.add = function (a, b) {
+  return a + b;
+};
+`;
+
+Deno.test("loads CommonJS as is by default", async () => {
+  const { loader } = await createLoader({ configPath }, {
+    entrypoints: [mainFile],
+  });
+  const url = loader.resolveSync(
+    "cjs-package",
+    import.meta.resolve("./testdata/main.ts"),
+    ResolutionMode.Import,
+  );
+  const response = await loader.load(url, RequestedModuleType.Default);
+  assertResponseText(response, cjsSource);
+});
+
+Deno.test("translates CommonJS to ESM", async () => {
+  const { loader } = await createLoader({ configPath, translateCjs: true }, {
+    entrypoints: [mainFile],
+  });
+  const url = loader.resolveSync(
+    "cjs-package",
+    import.meta.resolve("./testdata/main.ts"),
+    ResolutionMode.Import,
+  );
+  const response = await loader.load(
+    url,
+    RequestedModuleType.Default,
+  ) as ModuleLoadResponse;
+  const code = new TextDecoder().decode(response.code);
+  assert(code.includes("export default"), code);
+  assertMatch(code, /export\s*\{[^}]*\badd\b|export const add\b/);
+  assert(!code.startsWith("module.exports"), code);
+});
+
+Deno.test("persists the CommonJS analysis in the cache directory", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const { loader } = await createLoader({
+    configPath,
+    translateCjs: true,
+    cacheDir,
+  }, {
+    entrypoints: [mainFile],
+  });
+  const url = loader.resolveSync(
+    "cjs-package",
+    import.meta.resolve("./testdata/main.ts"),
+    ResolutionMode.Import,
+  );
+  await loader.load(url, RequestedModuleType.Default);
+  const entries = await Array.fromAsync(
+    Deno.readDir(cacheDir + "/cjs_analysis_cache_v1"),
+  );
+  assertEquals(entries.length, 1);
+  const entry = JSON.parse(
+    await Deno.readTextFile(
+      cacheDir + "/cjs_analysis_cache_v1/" + entries[0].name,
+    ),
+  );
+  assertEquals(entry.specifier, url);
+  assertEquals(entry.exports, ["add"]);
+});
@@ -0,0 +1,3 @@
+import { add } from "cjs-package";
+
+console.log(add(1, 2));
@@ -0,0 +1,54 @@
+import { assertEquals } from "@std/assert";
+import { fromFileUrl, join } from "@std/path";
+import { createLoader } from "../helpers.ts";
+import {
+  caPath,
+  cert,
+  key,
+  npmrcText,
+  registryResponse,
+} from "./testdata/registry.mjs";
+
+Deno.test("downloads npm packages using the CA from the .npmrc", async () => {
+  const requests: string[] = [];
+  const server = Deno.serve({
+    hostname: "127.0.0.1",
+    port: 0,
+    cert,
+    key,
+    onListen() {},
+  }, (request) => {
+    const { pathname } = new URL(request.url);
+    requests.push(pathname);
+    const response = registryResponse(registryUrl, pathname);
+    return new Response(response.body, {
+      status: response.status,
+      headers: { "content-type": response.contentType },
+    });
+  });
+  const registryUrl = `https://127.0.0.1:${server.addr.port}/`;
+  try {
+    const dir = await Deno.makeTempDir();
+    await Deno.writeTextFile(join(dir, "deno.json"), "{}");
+    await Deno.writeTextFile(
+      join(dir, ".npmrc"),
+      npmrcText(registryUrl, fromFileUrl(caPath)),
+    );
+    await Deno.writeTextFile(
+      join(dir, "main.js"),
+      `import "npm:cert-pkg@1.0.0";`,
+    );
+    const { loader, workspace } = await createLoader({
+      configPath: join(dir, "deno.json"),
+      cacheDir: await Deno.makeTempDir(),
+    }, {
+      entrypoints: [join(dir, "main.js")],
+    });
+    loader[Symbol.dispose]();
+    workspace[Symbol.dispose]();
+
+    assertEquals(requests, ["/cert-pkg", "/cert-pkg/-/cert-pkg-1.0.0.tgz"]);
+  } finally {
+    await server.shutdown();
+  }
+});
@@ -0,0 +1,48 @@
+// Fake npm registry that serves the `cert-pkg` package over https
+// with a certificate signed by the test CA in `ca.pem`.
+
+import { createHash } from "node:crypto";
+import { readFileSync } from "node:fs";
+
+const dir = new URL(".", import.meta.url);
+export const caPath = new URL("ca.pem", dir);
+export const cert = readFileSync(new URL("localhost.pem", dir), "utf8");
+export const key = readFileSync(new URL("localhost.key", dir), "utf8");
+const tarball = readFileSync(new URL("cert-pkg-1.0.0.tgz", dir));
+
+/** Gets the response for a request path to the registry at the url. */
+export function registryResponse(registryUrl, pathname) {
+  if (pathname === "/cert-pkg") {
+    const integrity = "sha512-" +
+      createHash("sha512").update(tarball).digest("base64");
+    const body = JSON.stringify({
+      name: "cert-pkg",
+      "dist-tags": { latest: "1.0.0" },
+      versions: {
+        "1.0.0": {
+          name: "cert-pkg",
+          version: "1.0.0",
+          dist: {
+            tarball: registryUrl + "cert-pkg/-/cert-pkg-1.0.0.tgz",
+            integrity,
+          },
+        },
+      },
+    });
+    return { status: 200, contentType: "application/json", body };
+  }
+  if (pathname === "/cert-pkg/-/cert-pkg-1.0.0.tgz") {
+    return {
+      status: 200,
+      contentType: "application/octet-stream",
+      body: tarball,
+    };
+  }
+  return { status: 404, contentType: "text/plain", body: "Not found" };
+}
+
+/** Gets the `.npmrc` that uses the registry and trusts the test CA. */
+export function npmrcText(registryUrl, cafile) {
+  const scope = registryUrl.replace(/^https:/, "");
+  return `registry=${registryUrl}\n${scope}:cafile=${cafile}\n`;
+}
@@ -0,0 +1,41 @@
+import { assertEquals } from "@std/assert";
+import { createLoader, ResolutionMode } from "../helpers.ts";
+
+const configPath = import.meta.dirname + "/testdata/deno.json";
+const referrer = import.meta.resolve("./testdata/main.js");
+
+function packageFile(name: string) {
+  return import.meta.resolve(`./testdata/node_modules/conditional/${name}`);
+}
+
+Deno.test("uses default conditions", async () => {
+  const { loader } = await createLoader({ configPath }, {
+    entrypoints: [],
+  });
+  assertEquals(
+    loader.resolveSync("conditional", referrer, ResolutionMode.Import),
+    packageFile("node.js"),
+  );
+  assertEquals(
+    loader.resolveSync("conditional", referrer, ResolutionMode.Require),
+    packageFile("node.js"),
+  );
+});
+
+Deno.test("uses separate import and require conditions", async () => {
+  const { loader } = await createLoader({
+    configPath,
+    importConditions: ["worker", "import"],
+    requireConditions: ["require"],
+  }, {
+    entrypoints: [],
+  });
+  assertEquals(
+    loader.resolveSync("conditional", referrer, ResolutionMode.Import),
+    packageFile("worker.js"),
+  );
+  assertEquals(
+    loader.resolveSync("conditional", referrer, ResolutionMode.Require),
+    packageFile("default.js"),
+  );
+});
@@ -0,0 +1,55 @@
+import { assertEquals } from "@std/assert";
+import {
+  assertResponseText,
+  createLoader,
+  type FetchInit,
+  RequestedModuleType,
+} from "../helpers.ts";
+
+Deno.test("uses custom fetch function", async () => {
+  const requests: [string, FetchInit][] = [];
+  const cacheDir = await Deno.makeTempDir();
+  const url = "https://example.com/mod.js";
+  const { loader } = await createLoader({
+    cacheDir,
+    noConfig: true,
+    fetch: (url, init) => {
+      requests.push([url, init]);
+      return Promise.resolve({
+        status: 200,
+        body: new TextEncoder().encode("console.log(1);"),
+        headers: new Headers({ "content-type": "application/javascript" }),
+      });
+    },
+  }, {
+    entrypoints: [url],
+  });
+
+  assertEquals(requests.map(([url]) => url), [url]);
+  assertResponseText(
+    await loader.load(url, RequestedModuleType.Default),
+    "console.log(1);",
+  );
+});
+
+Deno.test("supports returning a Response from custom fetch", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const url = "https://example.com/mod.js";
+  const { loader } = await createLoader({
+    cacheDir,
+    noConfig: true,
+    fetch: () =>
+      Promise.resolve(
+        new Response("console.log(2);", {
+          headers: { "content-type": "application/javascript" },
+        }),
+      ),
+  }, {
+    entrypoints: [url],
+  });
+
+  assertResponseText(
+    await loader.load(url, RequestedModuleType.Default),
+    "console.log(2);",
+  );
+});
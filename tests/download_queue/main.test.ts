@@ -0,0 +1,36 @@
+import { assertEquals } from "@std/assert";
+import { createLoader } from "../helpers.ts";
+
+Deno.test("limits and deduplicates downloads", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const requests: string[] = [];
+  let active = 0;
+  let maxActive = 0;
+  await createLoader({
+    cacheDir,
+    noConfig: true,
+    maxConcurrentDownloads: 2,
+    fetch: async (url) => {
+      requests.push(url);
+      active++;
+      maxActive = Math.max(maxActive, active);
+      await new Promise((resolve) => setTimeout(resolve, 10));
+      active--;
+      return new Response("import './shared.js';", {
+        headers: { "content-type": "application/javascript" },
+      });
+    },
+  }, {
+    entrypoints: [
+      "https://example.com/a.js",
+      "https://example.com/b.js",
+      "https://example.com/c.js",
+    ],
+  });
+
+  assertEquals(maxActive, 2);
+  assertEquals(
+    requests.filter((url) => url === "https://example.com/shared.js").length,
+    1,
+  );
+});
@@ -0,0 +1,52 @@
+import { assert, assertEquals } from "@std/assert";
+import { ResolutionMode, Workspace } from "../helpers.ts";
+
+Deno.test("surfaces dynamic import diagnostics when following dynamic imports", async () => {
+  const mainFile = import.meta.dirname + "/testdata/main.ts";
+  const workspace = new Workspace({
+    configPath: import.meta.dirname + "/testdata/deno.json",
+  });
+  {
+    const loader = await workspace.createLoader();
+    const diagnostics = await loader.addEntrypoints([mainFile]);
+    assertEquals(diagnostics, []);
+  }
+  {
+    const loader = await workspace.createLoader();
+    const diagnostics = await loader.addEntrypoints([mainFile], {
+      followDynamicImports: true,
+    });
+    assertEquals(diagnostics.length, 1);
+    assertEquals(diagnostics[0].kind, "resolution");
+    assertEquals(
+      diagnostics[0].referrer,
+      import.meta.resolve("./testdata/route.ts"),
+    );
+  }
+});
+
+Deno.test("resolves npm and jsr specifiers of dynamic imports synchronously", async () => {
+  const lazyFile = import.meta.dirname + "/testdata/lazy.ts";
+  const referrer = import.meta.resolve("./testdata/lazy.ts");
+  const workspace = new Workspace({
+    configPath: import.meta.dirname + "/testdata/deno.json",
+  });
+  const loader = await workspace.createLoader();
+  const diagnostics = await loader.addEntrypoints([lazyFile], {
+    followDynamicImports: true,
+  });
+  assertEquals(diagnostics, []);
+
+  const npmUrl = loader.resolveSync(
+    "npm:code-block-writer@13.0.3",
+    referrer,
+    ResolutionMode.Import,
+  );
+  assert(npmUrl.startsWith("file:///"));
+  const jsrUrl = loader.resolveSync(
+    "jsr:@david/code-block-writer@13.0.3",
+    referrer,
+    ResolutionMode.Import,
+  );
+  assert(jsrUrl.startsWith("https://jsr.io/@david/code-block-writer/13.0.3/"));
+});
@@ -0,0 +1,6 @@
+export async function loadWriters() {
+  return await Promise.all([
+    import("npm:code-block-writer@13.0.3"),
+    import("jsr:@david/code-block-writer@13.0.3"),
+  ]);
+}
@@ -0,0 +1,3 @@
+export async function loadRoute() {
+  return await import("./route.ts");
+}
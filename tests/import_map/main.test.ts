@@ -0,0 +1,61 @@
+import { assertEquals, assertRejects } from "@std/assert";
+import { createLoader, ResolutionMode, Workspace } from "../helpers.ts";
+
+const configPath = import.meta.dirname + "/testdata/deno.json";
+const mainFile = import.meta.dirname + "/testdata/main.ts";
+const referrer = import.meta.resolve("./testdata/main.ts");
+
+Deno.test("uses import map file", async () => {
+  const { loader } = await createLoader({
+    configPath,
+    importMap: import.meta.dirname + "/testdata/import_map.json",
+  }, {
+    entrypoints: [mainFile],
+  });
+  assertEquals(
+    loader.resolveSync("env", referrer, ResolutionMode.Import),
+    import.meta.resolve("./testdata/env.test.ts"),
+  );
+});
+
+Deno.test("uses inline import map", async () => {
+  const { loader } = await createLoader({
+    configPath,
+    importMap: {
+      imports: {
+        "env": import.meta.resolve("./testdata/env.prod.ts"),
+      },
+    },
+  }, {
+    entrypoints: [mainFile],
+  });
+  assertEquals(
+    loader.resolveSync("env", referrer, ResolutionMode.Import),
+    import.meta.resolve("./testdata/env.prod.ts"),
+  );
+});
+
+Deno.test("keeps config imports not in the import map", async () => {
+  const { loader } = await createLoader({
+    configPath,
+    importMap: import.meta.dirname + "/testdata/import_map.json",
+  }, {
+    entrypoints: [mainFile],
+  });
+  assertEquals(
+    loader.resolveSync("util", referrer, ResolutionMode.Import),
+    import.meta.resolve("./testdata/util.ts"),
+  );
+});
+
+Deno.test("errors for an invalid import map", async () => {
+  const workspace = new Workspace({
+    configPath,
+    importMap: import.meta.dirname + "/testdata/missing.json",
+  });
+  await assertRejects(
+    () => workspace.createLoader(),
+    Error,
+    "Failed reading import map",
+  );
+});
@@ -0,0 +1,3 @@
+import { value } from "env";
+
+console.log(value);
@@ -15,4 +15,13 @@ Deno.test("surfaces graph diagnostic", async () => {
     diagnostics[0].message.substring(0, expectedMessage.length),
     expectedMessage,
   );
+  assertEquals(diagnostics[0].kind, "resolution");
+  assertEquals(diagnostics[0].code, "ERR_UNRESOLVED_SPECIFIER");
+  assertEquals(diagnostics[0].specifier, "unknown");
+  assertEquals(
+    diagnostics[0].referrer,
+    import.meta.resolve("./testdata/main.ts"),
+  );
+  assertEquals(diagnostics[0].start, { line: 1, column: 8 });
+  assertEquals(diagnostics[0].end, { line: 1, column: 17 });
 });
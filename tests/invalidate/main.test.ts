@@ -0,0 +1,77 @@
+import { assert, assertEquals } from "@std/assert";
+import { join, toFileUrl } from "@std/path";
+import {
+  assertResponseText,
+  createLoader,
+  RequestedModuleType,
+} from "../helpers.ts";
+
+function graphSpecifiers(graph: any): string[] {
+  return graph.modules.map((m: any) => m.specifier).sort();
+}
+
+Deno.test("rebuilds dependents of files changed on disk", async () => {
+  const dir = await Deno.makeTempDir();
+  await Deno.writeTextFile(dir + "/deno.json", "{}");
+  await Deno.writeTextFile(dir + "/main.ts", `import "./dep.ts";`);
+  await Deno.writeTextFile(dir + "/dep.ts", "export const value = 1;");
+  const { loader } = await createLoader({
+    configPath: dir + "/deno.json",
+  }, {
+    entrypoints: [dir + "/main.ts"],
+  });
+  const mainUrl = toFileUrl(join(dir, "main.ts")).href;
+  const depUrl = toFileUrl(join(dir, "dep.ts")).href;
+  const otherUrl = toFileUrl(join(dir, "other.ts")).href;
+  assertEquals(graphSpecifiers(loader.getGraphUnstable()), [depUrl, mainUrl]);
+
+  await Deno.writeTextFile(dir + "/other.ts", "export const other = 2;");
+  await Deno.writeTextFile(
+    dir + "/dep.ts",
+    `export { other as value } from "./other.ts";`,
+  );
+  await loader.invalidate([dir + "/dep.ts"]);
+  assertEquals(await loader.addEntrypoints([]), []);
+
+  assertEquals(
+    graphSpecifiers(loader.getGraphUnstable()),
+    [depUrl, mainUrl, otherUrl],
+  );
+  assertEquals((loader.getGraphUnstable() as any).roots, [mainUrl]);
+  assertResponseText(
+    await loader.load(depUrl, RequestedModuleType.Default),
+    `export { other as value } from "./other.ts";\n`,
+    { skipSourceMap: true },
+  );
+});
+
+Deno.test("picks up package.json changes", async () => {
+  const dir = await Deno.makeTempDir();
+  await Deno.writeTextFile(dir + "/deno.json", "{}");
+  await Deno.writeTextFile(
+    dir + "/package.json",
+    JSON.stringify({ imports: { "#dep": "./a.js" } }),
+  );
+  await Deno.writeTextFile(dir + "/main.js", `import "#dep";`);
+  await Deno.writeTextFile(dir + "/a.js", "export const value = 'a';");
+  await Deno.writeTextFile(dir + "/b.js", "export const value = 'b';");
+  const { loader } = await createLoader({
+    configPath: dir + "/deno.json",
+  }, {
+    entrypoints: [dir + "/main.js"],
+  });
+  const aUrl = toFileUrl(join(dir, "a.js")).href;
+  const bUrl = toFileUrl(join(dir, "b.js")).href;
+  const mainUrl = toFileUrl(join(dir, "main.js")).href;
+  assert(graphSpecifiers(loader.getGraphUnstable()).includes(aUrl));
+
+  await Deno.writeTextFile(
+    dir + "/package.json",
+    JSON.stringify({ imports: { "#dep": "./b.js" } }),
+  );
+  await loader.invalidate([dir + "/package.json"]);
+  assertEquals(await loader.addEntrypoints([]), []);
+
+  assertEquals(graphSpecifiers(loader.getGraphUnstable()), [bUrl, mainUrl]);
+  assertEquals(loader.resolveSync("#dep", mainUrl), bUrl);
+});
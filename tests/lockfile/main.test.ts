@@ -0,0 +1,49 @@
+import { assert, assertRejects } from "@std/assert";
+import { createLoader } from "../helpers.ts";
+
+async function setupProject(port: number) {
+  const dir = await Deno.makeTempDir();
+  await Deno.writeTextFile(dir + "/deno.json", "{}");
+  await Deno.writeTextFile(
+    dir + "/main.ts",
+    `import "http://localhost:${port}/mod.js";`,
+  );
+  return dir;
+}
+
+Deno.test("writes remote dependencies to the lockfile", async () => {
+  await using server = Deno.serve((_request) => {
+    return new Response("console.log(1);", {
+      headers: {
+        "content-type": "application/javascript",
+      },
+    });
+  });
+  const dir = await setupProject(server.addr.port);
+  await createLoader({ configPath: dir + "/deno.json" }, {
+    entrypoints: [dir + "/main.ts"],
+  });
+
+  const lockfile = await Deno.readTextFile(dir + "/deno.lock");
+  assert(lockfile.includes(`http://localhost:${server.addr.port}/mod.js`));
+});
+
+Deno.test("errors when frozen lockfile would change", async () => {
+  await using server = Deno.serve((_request) => {
+    return new Response("console.log(1);", {
+      headers: {
+        "content-type": "application/javascript",
+      },
+    });
+  });
+  const dir = await setupProject(server.addr.port);
+  await Deno.writeTextFile(dir + "/deno.lock", `{ "version": "5" }`);
+  await assertRejects(
+    () =>
+      createLoader({ configPath: dir + "/deno.json", frozenLockfile: true }, {
+        entrypoints: [dir + "/main.ts"],
+      }),
+    Error,
+    "The lockfile is out of date.",
+  );
+});
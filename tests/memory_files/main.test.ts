@@ -0,0 +1,109 @@
+import { assertEquals, assertThrows } from "@std/assert";
+import {
+  assertResponseText,
+  MediaType,
+  RequestedModuleType,
+  ResolutionMode,
+  Workspace,
+} from "../helpers.ts";
+
+Deno.test("loads in-memory files", async () => {
+  const workspace = new Workspace({
+    configPath: import.meta.dirname + "/testdata/deno.json",
+  });
+  const loader = await workspace.createLoader();
+  const mainFile = import.meta.dirname + "/testdata/main.ts";
+  const generatedFile = import.meta.dirname + "/testdata/routes.gen.ts";
+  loader.addMemoryFile(
+    generatedFile,
+    "export const routes: number = 1;",
+  );
+  const diagnostics = await loader.addEntrypoints([mainFile]);
+  assertEquals(diagnostics, []);
+
+  const url = loader.resolveSync(
+    "./routes.gen.ts",
+    import.meta.resolve("./testdata/main.ts"),
+    ResolutionMode.Import,
+  );
+  assertEquals(url, import.meta.resolve("./testdata/routes.gen.ts"));
+  const response = await loader.load(url, RequestedModuleType.Default);
+  assertResponseText(
+    response,
+    "export const routes = 1;\n",
+    { skipSourceMap: true },
+  );
+});
+
+Deno.test("uses provided media type for in-memory files", async () => {
+  const workspace = new Workspace({
+    configPath: import.meta.dirname + "/testdata/deno.json",
+  });
+  const loader = await workspace.createLoader();
+  const url = import.meta.resolve("./testdata/env");
+  loader.addMemoryFile(url, "export const a: number = 1;", MediaType.TypeScript);
+  const response = await loader.load(url, RequestedModuleType.Default);
+  if (response.kind !== "module") {
+    throw new Error("Fail");
+  }
+  assertEquals(response.mediaType, MediaType.TypeScript);
+
+  assertEquals(loader.removeMemoryFile(url), true);
+  assertEquals(loader.removeMemoryFile(url), false);
+});
+
+Deno.test("round-trips the media type of in-memory files", async () => {
+  const workspace = new Workspace({
+    configPath: import.meta.dirname + "/testdata/deno.json",
+  });
+  const loader = await workspace.createLoader();
+  const url = import.meta.resolve("./testdata/types.d.ts");
+  loader.addMemoryFile(url, "export type A = string;", MediaType.Dts);
+  const response = await loader.load(url, RequestedModuleType.Default);
+  if (response.kind !== "module") {
+    throw new Error("Fail");
+  }
+  assertEquals(response.mediaType, MediaType.Dts);
+
+  // can't be distinguished from JavaScript without an extension
+  assertThrows(
+    () =>
+      loader.addMemoryFile(
+        import.meta.resolve("./testdata/script"),
+        "module.exports = 1;",
+        MediaType.Cjs,
+      ),
+    Error,
+    "requires a matching extension",
+  );
+});
+
+Deno.test("reloads invalidated in-memory files", async () => {
+  const workspace = new Workspace({
+    configPath: import.meta.dirname + "/testdata/deno.json",
+  });
+  const loader = await workspace.createLoader();
+  const mainFile = import.meta.dirname + "/testdata/main.ts";
+  const generatedUrl = import.meta.resolve("./testdata/routes.gen.ts");
+  loader.addMemoryFile(generatedUrl, "export const routes: number = 1;");
+  assertEquals(await loader.addEntrypoints([mainFile]), []);
+  assertResponseText(
+    await loader.load(generatedUrl, RequestedModuleType.Default),
+    "export const routes = 1;\n",
+    { skipSourceMap: true },
+  );
+
+  loader.addMemoryFile(generatedUrl, "export const routes: number = 2;");
+  await loader.invalidate([generatedUrl]);
+  assertEquals(await loader.addEntrypoints([]), []);
+  assertResponseText(
+    await loader.load(generatedUrl, RequestedModuleType.Default),
+    "export const routes = 2;\n",
+    { skipSourceMap: true },
+  );
+  // the dependent root was rebuilt
+  assertEquals(
+    (loader.getGraphUnstable() as any).roots,
+    [import.meta.resolve("./testdata/main.ts")],
+  );
+});
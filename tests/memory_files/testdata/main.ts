@@ -0,0 +1,3 @@
+import { routes } from "./routes.gen.ts";
+
+console.log(routes);
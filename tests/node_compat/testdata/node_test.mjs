@@ -3,6 +3,10 @@
 // runtime detection → dynamic import → WASM instantiation → loader operations.
 
 import assert from "node:assert/strict";
+import { mkdtemp, writeFile } from "node:fs/promises";
+import { createServer } from "node:https";
+import { tmpdir } from "node:os";
+import { join } from "node:path";
 import { fileURLToPath } from "node:url";
 import {
   Workspace,
@@ -12,6 +16,13 @@ import {
   MediaType,
   ResolveError,
 } from "../../../src/mod.ts";
+import {
+  caPath,
+  cert,
+  key,
+  npmrcText,
+  registryResponse,
+} from "../../client_cert/testdata/registry.mjs";
 
 const configPath = fileURLToPath(new URL("deno.json", import.meta.url));
 const mainTsPath = fileURLToPath(new URL("main.ts", import.meta.url));
@@ -133,6 +144,47 @@ await test("loading non-existent module throws", async () => {
   );
 });
 
+// ---------- npmrc CA ----------
+
+await test("downloads npm packages using the CA from the .npmrc", async () => {
+  const requests = [];
+  const server = createServer({ cert, key }, (req, res) => {
+    const { pathname } = new URL(req.url, registryUrl);
+    requests.push(pathname);
+    const response = registryResponse(registryUrl, pathname);
+    res.writeHead(response.status, { "content-type": response.contentType });
+    res.end(response.body);
+  });
+  await new Promise((resolve) => server.listen(0, "127.0.0.1", resolve));
+  const registryUrl = `https://127.0.0.1:${server.address().port}/`;
+  try {
+    const dir = await mkdtemp(join(tmpdir(), "deno-loader-"));
+    await writeFile(join(dir, "deno.json"), "{}");
+    await writeFile(
+      join(dir, ".npmrc"),
+      npmrcText(registryUrl, fileURLToPath(caPath)),
+    );
+    await writeFile(join(dir, "main.js"), `import "npm:cert-pkg@1.0.0";`);
+    const workspace = new Workspace({
+      configPath: join(dir, "deno.json"),
+      cacheDir: await mkdtemp(join(tmpdir(), "deno-loader-cache-")),
+    });
+    const loader = await workspace.createLoader();
+    const diagnostics = await loader.addEntrypoints([join(dir, "main.js")]);
+    loader[Symbol.dispose]();
+    workspace[Symbol.dispose]();
+
+    assert.deepStrictEqual(diagnostics, []);
+    assert.deepStrictEqual(requests, [
+      "/cert-pkg",
+      "/cert-pkg/-/cert-pkg-1.0.0.tgz",
+    ]);
+  } finally {
+    server.closeAllConnections();
+    await new Promise((resolve) => server.close(resolve));
+  }
+});
+
 // ---------- cleanup ----------
 
 loader[Symbol.dispose]();
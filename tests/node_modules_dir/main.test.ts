@@ -0,0 +1,55 @@
+import { assert, assertEquals } from "@std/assert";
+import { createLoader } from "../helpers.ts";
+
+async function setupProject() {
+  const dir = await Deno.makeTempDir();
+  await Deno.writeTextFile(dir + "/deno.json", `{ "lock": false }`);
+  await Deno.writeTextFile(
+    dir + "/main.ts",
+    `import "npm:code-block-writer@13.0.3";`,
+  );
+  return dir;
+}
+
+async function exists(path: string) {
+  try {
+    await Deno.stat(path);
+    return true;
+  } catch (err) {
+    if (err instanceof Deno.errors.NotFound) {
+      return false;
+    }
+    throw err;
+  }
+}
+
+Deno.test("installs npm packages into node_modules when auto", async () => {
+  const dir = await setupProject();
+  await createLoader({
+    configPath: dir + "/deno.json",
+    cacheDir: await Deno.makeTempDir(),
+    nodeModulesDir: "auto",
+  }, {
+    entrypoints: [dir + "/main.ts"],
+  });
+
+  assert(
+    await exists(
+      dir +
+        "/node_modules/.deno/code-block-writer@13.0.3/node_modules/code-block-writer/package.json",
+    ),
+  );
+});
+
+Deno.test("uses the global npm cache when none", async () => {
+  const dir = await setupProject();
+  await createLoader({
+    configPath: dir + "/deno.json",
+    cacheDir: await Deno.makeTempDir(),
+    nodeModulesDir: "none",
+  }, {
+    entrypoints: [dir + "/main.ts"],
+  });
+
+  assertEquals(await exists(dir + "/node_modules"), false);
+});
@@ -0,0 +1,111 @@
+import { assertEquals, assertRejects } from "@std/assert";
+import {
+  createLoaderWithDiagnostics,
+  RequestedModuleType,
+} from "../helpers.ts";
+
+Deno.test("denies loads not allowed by the permissions", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const mainFile = import.meta.dirname + "/testdata/allowed/main.ts";
+  const { loader, diagnostics } = await createLoaderWithDiagnostics({
+    cacheDir,
+    configPath: import.meta.dirname + "/testdata/deno.json",
+    permissions: {
+      read: [import.meta.dirname + "/testdata/allowed"],
+      net: ["jsr.io"],
+    },
+  }, {
+    entrypoints: [mainFile],
+  });
+
+  assertEquals(
+    diagnostics.map((d) => [d.code, d.specifier]),
+    [
+      ["ERR_ACCESS_DENIED", import.meta.resolve("./testdata/denied/secret.ts")],
+      ["ERR_ACCESS_DENIED", "https://example.com/mod.js"],
+    ],
+  );
+
+  const err = await assertRejects(
+    () =>
+      loader.load(
+        import.meta.resolve("./testdata/denied/secret.ts"),
+        RequestedModuleType.Default,
+      ),
+    Error,
+    "Requires read access",
+  );
+  assertEquals((err as { code?: string }).code, "ERR_ACCESS_DENIED");
+});
+
+Deno.test("only exempts the loader's own npm folders", async () => {
+  const { loader } = await createLoaderWithDiagnostics({
+    configPath: import.meta.dirname + "/testdata/deno.json",
+    permissions: {
+      read: [import.meta.dirname + "/testdata/allowed"],
+    },
+  }, {
+    entrypoints: [],
+  });
+  const err = await assertRejects(
+    () =>
+      loader.load(
+        import.meta.resolve("./testdata/denied/node_modules/pkg/index.js"),
+        RequestedModuleType.Default,
+      ),
+    Error,
+    "Requires read access",
+  );
+  assertEquals((err as { code?: string }).code, "ERR_ACCESS_DENIED");
+});
+
+Deno.test("normalizes allowed paths", async () => {
+  const { loader } = await createLoaderWithDiagnostics({
+    configPath: import.meta.dirname + "/testdata/deno.json",
+    permissions: {
+      read: [import.meta.dirname + "/testdata/allowed/../denied"],
+    },
+  }, {
+    entrypoints: [],
+  });
+  const response = await loader.load(
+    import.meta.resolve("./testdata/denied/secret.ts"),
+    RequestedModuleType.Default,
+  );
+  assertEquals(response.kind, "module");
+});
+
+Deno.test("denies redirects to hosts not allowed", async () => {
+  await using target = Deno.serve({ port: 0 }, (_request) => {
+    return new Response("export const value = 1;", {
+      headers: {
+        "content-type": "application/javascript",
+      },
+    });
+  });
+  await using server = Deno.serve({ port: 0 }, (_request) => {
+    return Response.redirect(
+      `http://localhost:${target.addr.port}/mod.js`,
+      302,
+    );
+  });
+  const { loader } = await createLoaderWithDiagnostics({
+    cacheDir: await Deno.makeTempDir(),
+    configPath: import.meta.dirname + "/testdata/deno.json",
+    permissions: {
+      net: [`localhost:${server.addr.port}`],
+    },
+  }, {
+    entrypoints: [],
+  });
+  const err = await assertRejects(
+    () =>
+      loader.load(
+        `http://localhost:${server.addr.port}/mod.js`,
+        RequestedModuleType.Default,
+      ),
+    Error,
+    "Requires net access",
+  );
+  assertEquals((err as { code?: string }).code, "ERR_ACCESS_DENIED");
+});
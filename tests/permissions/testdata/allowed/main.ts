@@ -0,0 +1,2 @@
+import "../denied/secret.ts";
+import "https://example.com/mod.js";
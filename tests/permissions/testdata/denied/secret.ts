@@ -0,0 +1 @@
+export const secret = 1;
@@ -0,0 +1,127 @@
+import { assertEquals } from "@std/assert";
+import { createLoader, type ProgressEvent } from "../helpers.ts";
+
+Deno.test("reports progress events", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const events: ProgressEvent[] = [];
+  const url = "https://example.com/mod.js";
+  await createLoader({
+    cacheDir,
+    noConfig: true,
+    onProgress: (event) => events.push(event),
+    fetch: () =>
+      Promise.resolve(
+        new Response("console.log(1);", {
+          headers: { "content-type": "application/javascript" },
+        }),
+      ),
+  }, {
+    entrypoints: [url],
+  });
+
+  assertEquals(
+    events.filter((e) => e.kind !== "moduleLoaded"),
+    [
+      { kind: "downloadStart", url },
+      { kind: "downloadEnd", url, status: 200, bytes: 15 },
+    ],
+  );
+  assertEquals(events.at(-1), {
+    kind: "moduleLoaded",
+    specifier: url,
+    modulesDone: 1,
+    modulesTotal: 1,
+  });
+});
+
+Deno.test("reports cache hits for cached modules", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const url = "https://example.com/mod.js";
+  const fetch = () =>
+    Promise.resolve(
+      new Response("console.log(1);", {
+        headers: { "content-type": "application/javascript" },
+      }),
+    );
+  await createLoader({ cacheDir, noConfig: true, fetch }, {
+    entrypoints: [url],
+  });
+
+  const events: ProgressEvent[] = [];
+  await createLoader({
+    cacheDir,
+    noConfig: true,
+    onProgress: (event) => events.push(event),
+    fetch,
+  }, {
+    entrypoints: [url],
+  });
+
+  assertEquals(
+    events.filter((e) => e.kind !== "moduleLoaded"),
+    [{ kind: "cacheHit", url }],
+  );
+});
+
+Deno.test("reports cache hits for npm packages once installed", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const specifier = "npm:code-block-writer@13.0.3";
+  const npmEvents = (events: ProgressEvent[]) =>
+    events.filter((e) =>
+      e.kind === "npmPackageResolved" ||
+      (e.kind === "cacheHit" && e.url.startsWith("npm:"))
+    );
+
+  const coldEvents: ProgressEvent[] = [];
+  await createLoader({
+    cacheDir,
+    noConfig: true,
+    nodeModulesDir: "none",
+    onProgress: (event) => coldEvents.push(event),
+  }, {
+    entrypoints: [specifier],
+  });
+  assertEquals(npmEvents(coldEvents), [
+    { kind: "npmPackageResolved", package: "code-block-writer@13.0.3" },
+  ]);
+
+  const warmEvents: ProgressEvent[] = [];
+  await createLoader({
+    cacheDir,
+    noConfig: true,
+    nodeModulesDir: "none",
+    onProgress: (event) => warmEvents.push(event),
+  }, {
+    entrypoints: [specifier],
+  });
+  assertEquals(npmEvents(warmEvents), [
+    { kind: "npmPackageResolved", package: "code-block-writer@13.0.3" },
+    { kind: "cacheHit", url: "npm:code-block-writer@13.0.3" },
+  ]);
+});
+
+Deno.test("logs nothing when quiet", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const messages: unknown[] = [];
+  const originalError = console.error;
+  console.error = (...args: unknown[]) => messages.push(args);
+  try {
+    await createLoader({
+      cacheDir,
+      noConfig: true,
+      quiet: true,
+      fetch: () =>
+        Promise.resolve(
+          new Response("console.log(1);", {
+            headers: { "content-type": "application/javascript" },
+          }),
+        ),
+    }, {
+      entrypoints: ["https://example.com/mod.js"],
+    });
+  } finally {
+    console.error = originalError;
+  }
+
+  assertEquals(messages, []);
+});
@@ -0,0 +1,56 @@
+import { assert, assertEquals } from "@std/assert";
+import { createLoaderWithDiagnostics, Workspace } from "../helpers.ts";
+
+Deno.test("surfaces mapped resolution diagnostics", async () => {
+  const mainFile = import.meta.dirname + "/testdata/main/main.ts";
+  const { loader } = await createLoaderWithDiagnostics({
+    configPath: import.meta.dirname + "/testdata/main/deno.json",
+  }, {
+    entrypoints: [mainFile],
+  });
+
+  const diagnostics = loader.takeResolutionDiagnostics();
+  assertEquals(diagnostics.length, 1);
+  assert(diagnostics[0].message.includes("@denotest/add"));
+  assertEquals(
+    diagnostics[0].referrer,
+    import.meta.resolve("./testdata/main/main.ts"),
+  );
+  assertEquals(diagnostics[0].start, { line: 1, column: 21 });
+  assertEquals(
+    diagnostics[0].configFile,
+    import.meta.resolve("./testdata/main/deno.json"),
+  );
+
+  // taking the diagnostics clears them
+  assertEquals(loader.takeResolutionDiagnostics(), []);
+});
+
+Deno.test("collects resolution diagnostics per loader", async () => {
+  const workspace = new Workspace({
+    configPath: import.meta.dirname + "/testdata/main/deno.json",
+  });
+  const loader = await workspace.createLoader();
+  const otherLoader = await workspace.createLoader();
+  await loader.addEntrypoints([
+    import.meta.dirname + "/testdata/main/main.ts",
+  ]);
+
+  assertEquals(loader.takeResolutionDiagnostics().length, 1);
+  assertEquals(otherLoader.takeResolutionDiagnostics(), []);
+});
+
+Deno.test("keeps diagnostics separate for concurrent loaders", async () => {
+  const workspace = new Workspace({
+    configPath: import.meta.dirname + "/testdata/main/deno.json",
+  });
+  const loader = await workspace.createLoader();
+  const otherLoader = await workspace.createLoader();
+  await Promise.all([
+    loader.addEntrypoints([import.meta.dirname + "/testdata/main/main.ts"]),
+    otherLoader.addEntrypoints([import.meta.dirname + "/testdata/add/mod.ts"]),
+  ]);
+
+  assertEquals(loader.takeResolutionDiagnostics().length, 1);
+  assertEquals(otherLoader.takeResolutionDiagnostics(), []);
+});
@@ -0,0 +1,3 @@
+import { add } from "jsr:@denotest/add@^2.0.0";
+
+console.log(add(1, 2));
@@ -0,0 +1,56 @@
+import { assertEquals } from "@std/assert";
+import { createLoader, ResolutionKind, ResolutionMode } from "../helpers.ts";
+
+const mainFile = import.meta.dirname + "/testdata/main.ts";
+
+Deno.test("resolves package types", async () => {
+  const { loader } = await createLoader({
+    configPath: import.meta.dirname + "/testdata/deno.json",
+  }, {
+    entrypoints: [mainFile],
+  });
+
+  const referrer = import.meta.resolve("./testdata/main.ts");
+  assertEquals(
+    loader.resolveSync(
+      "typed-package",
+      referrer,
+      ResolutionMode.Import,
+      ResolutionKind.Types,
+    ),
+    import.meta.resolve("./testdata/node_modules/typed-package/index.d.ts"),
+  );
+  assertEquals(
+    loader.resolveSync(
+      "typed-package",
+      referrer,
+      ResolutionMode.Import,
+      ResolutionKind.Execution,
+    ),
+    import.meta.resolve("./testdata/node_modules/typed-package/index.js"),
+  );
+});
+
+Deno.test("resolves @ts-types directive", async () => {
+  const { loader } = await createLoader({
+    configPath: import.meta.dirname + "/testdata/deno.json",
+    includeTypes: true,
+  }, {
+    entrypoints: [mainFile],
+  });
+
+  const referrer = import.meta.resolve("./testdata/main.ts");
+  assertEquals(
+    loader.resolveSync(
+      "./local.js",
+      referrer,
+      ResolutionMode.Import,
+      ResolutionKind.Types,
+    ),
+    import.meta.resolve("./testdata/local.d.ts"),
+  );
+  assertEquals(
+    loader.resolveSync("./local.js", referrer, ResolutionMode.Import),
+    import.meta.resolve("./testdata/local.js"),
+  );
+});
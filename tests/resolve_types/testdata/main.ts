@@ -0,0 +1,5 @@
+// @ts-types="./local.d.ts"
+import { value } from "./local.js";
+import { add } from "typed-package";
+
+console.log(add(value, 1));
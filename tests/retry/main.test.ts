@@ -0,0 +1,59 @@
+import { assertEquals } from "@std/assert";
+import {
+  assertResponseText,
+  createLoader,
+  createLoaderWithDiagnostics,
+  RequestedModuleType,
+} from "../helpers.ts";
+
+Deno.test("retries failed downloads", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const url = "https://example.com/mod.js";
+  let attempts = 0;
+  const { loader } = await createLoader({
+    cacheDir,
+    noConfig: true,
+    retry: { initialDelayMs: 1 },
+    fetch: () => {
+      attempts++;
+      if (attempts === 1) {
+        return Promise.resolve(new Response(null, { status: 503 }));
+      } else if (attempts === 2) {
+        return Promise.reject(new Error("connection reset"));
+      }
+      return Promise.resolve(
+        new Response("console.log(1);", {
+          headers: { "content-type": "application/javascript" },
+        }),
+      );
+    },
+  }, {
+    entrypoints: [url],
+  });
+
+  assertEquals(attempts, 3);
+  assertResponseText(
+    await loader.load(url, RequestedModuleType.Default),
+    "console.log(1);",
+  );
+});
+
+Deno.test("stops retrying after max retries", async () => {
+  const cacheDir = await Deno.makeTempDir();
+  const url = "https://example.com/mod.js";
+  let attempts = 0;
+  const { diagnostics } = await createLoaderWithDiagnostics({
+    cacheDir,
+    noConfig: true,
+    retry: { maxRetries: 1, initialDelayMs: 1 },
+    fetch: () => {
+      attempts++;
+      return Promise.resolve(new Response(null, { status: 500 }));
+    },
+  }, {
+    entrypoints: [url],
+  });
+
+  assertEquals(attempts, 2);
+  assertEquals(diagnostics.length, 1);
+});
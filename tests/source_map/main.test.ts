@@ -77,6 +77,36 @@ Deno.test("source map not returned for plain JavaScript", async () => {
   assertEquals(response.sourceMap, undefined);
 });
 
+Deno.test("referenced source map returned for plain JavaScript", async () => {
+  const bundledJs = import.meta.dirname + "/testdata/bundled.js";
+  const { loader } = await createLoader({ configPath }, {
+    entrypoints: [bundledJs],
+  });
+
+  const response = await resolveAndLoad(loader, bundledJs);
+
+  assertEquals(response.kind, "module");
+  const sm = parseSourceMap(response);
+  assertEquals(sm.sources, ["original.ts"]);
+  const code = new TextDecoder().decode(response.code);
+  assert(code.includes("//# sourceMappingURL=bundled.js.map"));
+});
+
+Deno.test("data url source map returned for CSS", async () => {
+  const { loader } = await createLoader({ configPath }, {
+    entrypoints: [import.meta.dirname + "/testdata/simple.ts"],
+  });
+
+  const response = await resolveAndLoad(
+    loader,
+    import.meta.dirname + "/testdata/styles.css",
+  );
+
+  assertEquals(response.kind, "module");
+  const sm = parseSourceMap(response);
+  assertEquals(sm.sources, ["styles.scss"]);
+});
+
 Deno.test("source map not returned for JSON", async () => {
   const simpleTs = import.meta.dirname + "/testdata/simple.ts";
   const { loader } = await createLoader({ configPath }, {
@@ -174,6 +204,41 @@ Deno.test("inline source map comment remains in code", async () => {
   );
 });
 
+Deno.test("inline source map comment removed with external source maps", async () => {
+  const { loader } = await createLoader({ configPath, sourceMap: "external" }, {
+    entrypoints: [import.meta.dirname + "/testdata/simple.ts"],
+  });
+
+  const response = await resolveAndLoad(
+    loader,
+    import.meta.dirname + "/testdata/simple.ts",
+  );
+
+  const code = new TextDecoder().decode(response.code);
+  assert(
+    !code.includes("//# sourceMappingURL="),
+    "inline source map comment should be removed from code",
+  );
+  const sm = parseSourceMap(response);
+  assertEquals(sm.sources, ["simple.ts"]);
+});
+
+Deno.test("inline source map comment removed from translated CommonJS", async () => {
+  const file = import.meta.dirname + "/testdata/add.cts";
+  const { loader } = await createLoader({
+    configPath,
+    sourceMap: "external",
+    translateCjs: true,
+  }, {
+    entrypoints: [file],
+  });
+
+  const response = await resolveAndLoad(loader, file);
+
+  const code = new TextDecoder().decode(response.code);
+  assert(!code.includes("//# sourceMappingURL="), code);
+});
+
 Deno.test("source map for external node: specifier is not applicable", async () => {
   const { loader } = await createLoader({ configPath }, {
     entrypoints: [import.meta.dirname + "/testdata/simple.ts"],
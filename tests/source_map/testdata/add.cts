@@ -0,0 +1,3 @@
+module.exports.add = function (a: number, b: number): number {
+  return a + b;
+};
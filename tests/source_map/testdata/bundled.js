@@ -0,0 +1,2 @@
+console.log("bundled");
+//# sourceMappingURL=bundled.js.map